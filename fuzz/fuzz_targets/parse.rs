@@ -0,0 +1,29 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use bfscript_v2::bfscript::*;
+
+// Tokenizing and parsing arbitrary bytes has to end with tokens or errors, never a panic or a hang
+fuzz_target!(|input: &[u8]| {
+	let mut tokenizer = Tokenizer::new(input);
+
+	loop {
+		let start = tokenizer.position;
+
+		match tokenizer.next() {
+			Ok(token) if token.kind == TokenType::EndOfFile => break,
+
+			// Every token takes at least one byte, so reading always comes to an end
+			Ok(token) => assert!(tokenizer.position > start && token.end <= input.len()),
+
+			Err(error) => {
+				assert!(error.span.line >= 1 && error.span.column >= 1);
+
+				break;
+			}
+		}
+	}
+
+	Parser::new(Tokenizer::new(input)).parse();
+});
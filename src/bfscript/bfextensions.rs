@@ -13,7 +13,7 @@ pub fn bf_open_file(state: &mut Interpreter) {
 
     // println!("Opening file: {}", name);
     // open if exists and create it if it doesn't
-	let mut file = match std::path::Path::exists(std::path::Path::new(name))  {
+	let file = match std::path::Path::exists(std::path::Path::new(name))  {
         true => File::open(name).unwrap_or_else(|_| {
             panic!("Could not open file: {}", name);
         }),
@@ -42,7 +42,7 @@ pub fn bf_write(state: &mut Interpreter) {
     };
 
     // write single byte
-    match file.write(data.as_bytes()) {
+    match file.write_all(data.as_bytes()) {
         Ok(_) => {
             state.cells[state.pointer as usize] = 1;
         },
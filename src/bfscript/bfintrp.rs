@@ -1,6 +1,6 @@
 #![allow(dead_code, unused_imports)]
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fs::File;
 use std::io::prelude::*;
 use std::vec::Vec;
@@ -27,21 +27,23 @@ pub struct Interpreter<'a> {
 	pub skiplevel: i32,
 	pub extended_mode: bool,
 
+	// Read instead of stdin when set
+	pub input: Option<VecDeque<u8>>,
+
+	// Written instead of stdout when set
+	pub output: Option<Vec<u8>>,
+
 	pub commands: HashMap<u8, fn(&mut Interpreter)>,
 	pub files: Vec<File>,
 }
 
 impl<'a> Interpreter<'a> {
-	pub fn new(src: &'a [u8]) -> Interpreter {
-		let mut vc = Vec::new();
-
-		vc.push(0);
-		
+	pub fn new(src: &'a [u8]) -> Interpreter<'a> {
 		return Interpreter {
 			position: 0,
 			size: src.len(),
 			source: src,
-			cells: vc,
+			cells: vec![0],
 			pointer: 0,
 			level: 0,
 			stack: Vec::<usize>::new(),
@@ -49,6 +51,8 @@ impl<'a> Interpreter<'a> {
 			skiplevel: 0,
 			
 			extended_mode: false,
+			input: None,
+			output: None,
 			commands: HashMap::new(),
 			files: Vec::new(),
 		};
@@ -87,13 +91,31 @@ impl<'a> Interpreter<'a> {
 		}
 
 		if self.source[self.position] == b',' {
-			self.cells[self.pointer as usize] = std::io::stdin().bytes().next().unwrap().unwrap();
+			let mut byte = [0u8];
+
+			// End of input reads as zero
+			match self.input.as_mut() {
+				Some(input) => byte[0] = input.pop_front().unwrap_or(0),
+
+				None => if std::io::stdin().read_exact(&mut byte).is_err() {
+					byte[0] = 0;
+				}
+			}
+
+			self.cells[self.pointer as usize] = byte[0];
 		}
 
 		if self.source[self.position] == b'.' {
-			print!("{}", self.cells[self.pointer as usize] as char);
+			// Written as a raw byte, so multi-cell UTF-8 characters come out whole
+			match &mut self.output {
+				Some(output) => output.push(self.cells[self.pointer as usize]),
+
+				None => {
+					std::io::stdout().write_all(&[self.cells[self.pointer as usize]]).unwrap();
 
-			std::io::stdout().flush().unwrap();
+					std::io::stdout().flush().unwrap();
+				}
+			}
 		}
 
 		if self.source[self.position] == b'[' {
@@ -199,8 +221,6 @@ impl<'a> Interpreter<'a> {
 		data += format!("Pointer [{}]\n", self.pointer).as_str();
 		data += format!("Cells [{}]\n\n", self.cells.len()).as_str();
 
-		let mut l = 1;
-		
 		for i in 0..self.cells.len() {
 			data += if i == self.pointer as usize {
 				format!(">{}< ", self.cells[i])
@@ -209,11 +229,9 @@ impl<'a> Interpreter<'a> {
 				format!("{} ", self.cells[i])
 			}.as_str();
 
-			if l % 10 == 0 {
+			if (i + 1) % 10 == 0 {
 				data += "\n";
 			}
-
-			l += 1;
 		}
 
 		file.write_all(data.as_bytes()).unwrap();
@@ -1,6 +1,6 @@
 #![allow(unused_variables, dead_code)]
 
-use std::borrow::BorrowMut;
+use std::collections::HashMap;
 
 use super::*;
 
@@ -20,7 +20,142 @@ impl ValueInfo {
     }
 }
 
-#[derive(Debug)]
+// Cells taken by each integer type, lowest cell first, int is the same as u8
+// Characters are single cell numbers too, so they can be used in arithmetic
+pub fn integer_size(type_name: &str) -> Option<usize> {
+    return match type_name {
+        "int" | "u8" | "i8" | "char" => Some(1),
+        "u16" | "i16" => Some(2),
+        "u32" => Some(4),
+        _ => None
+    };
+}
+
+// Values an integer type can hold
+pub fn integer_range(type_name: &str) -> Option<(i64, i64)> {
+    return match type_name {
+        "int" | "u8" | "char" => Some((0, 0xFF)),
+        "i8" => Some((-0x80, 0x7F)),
+        "u16" => Some((0, 0xFFFF)),
+        "i16" => Some((-0x8000, 0x7FFF)),
+        "u32" => Some((0, 0xFFFFFFFF)),
+        _ => None
+    };
+}
+
+// Value of a number or character literal, which may be negated
+fn literal_value(expr: &Expression) -> Option<i64> {
+    if expr.kind == ExpressionType::Unary && expr.operator.as_ref().unwrap().kind == TokenType::Minus {
+        return literal_value(expr.right.as_ref().unwrap()).map(|value| -value);
+    }
+
+    if expr.kind != ExpressionType::Literal {
+        return None;
+    }
+
+    let token = expr.value.as_ref().unwrap();
+
+    return match token.kind {
+        TokenType::Number => Some(token.number),
+        TokenType::Char => Some(token.char as i64),
+        _ => None
+    };
+}
+
+// Literals are checked against the exact range of the type they're stored in
+fn range_error(type_name: &str, expr: &Expression) -> Option<String> {
+    if let (Some((min, max)), Some(value)) = (integer_range(type_name), literal_value(expr)) {
+        if value < min || value > max {
+            return Some(format!("{} is out of range for {}, which holds {} to {}", value, type_name, min, max));
+        }
+    }
+
+    return None;
+}
+
+// Parameters are "any", "int" for a single cell integer, "string" or "constant" for a number literal
+// Builtins returning a string are as long as their constant argument
+const BUILTINS: [(&str, &[&str], &str); 7] = [
+    ("print", &["any"], "void"),
+    ("print_int", &["integer"], "void"),
+    ("read", &["constant"], "string"),
+    ("read_line", &["constant"], "string"),
+    ("read_int", &[], "int"),
+    ("open", &["string"], "int"),
+    ("write", &["int", "any"], "int")
+];
+
+pub fn builtin(name: &str) -> Option<(&'static [&'static str], &'static str)> {
+    return BUILTINS.iter().find(|builtin| builtin.0 == name).map(|builtin| (builtin.1, builtin.2));
+}
+
+// Types that can be written in declarations, arrays add [] to an integer type
+pub fn known_type(type_name: &str) -> bool {
+    return type_name == "string" || integer_size(type_name).is_some();
+}
+
+// Signed integers are stored in two's complement
+pub fn is_signed(type_name: &str) -> bool {
+    return type_name == "i8" || type_name == "i16";
+}
+
+// Integers can be stored in any integer type at least as wide, characters and numbers convert freely since both take a cell
+// Strings hold any string that fits, a character is a one character string and a one character string is a character
+// Values that already failed to type check fit anywhere, so one mistake isn't reported twice
+pub fn assignable(target: &ValueInfo, value: &ValueInfo) -> bool {
+    if target.type_name == "Unknown" || value.type_name == "Unknown" {
+        return true;
+    }
+
+    if let (Some(target), Some(value)) = (integer_size(&target.type_name), integer_size(&value.type_name)) {
+        return value <= target;
+    }
+
+    if integer_size(&target.type_name) == Some(1) && value.type_name == "string" {
+        return value.size == 1;
+    }
+
+    if target.type_name == "string" && (value.type_name == "string" || value.type_name == "char") {
+        return value.size <= target.size;
+    }
+
+    return target.type_name == value.type_name;
+}
+
+// Integers, one character strings, or a value that already failed to type check
+fn numeric(value: &ValueInfo) -> bool {
+    return value.type_name == "Unknown" || integer_size(&value.type_name).is_some() || (value.type_name == "string" && value.size == 1);
+}
+
+// How a type is shown in errors, strings with their length since that's what makes them fit or not
+fn describe(value: &ValueInfo) -> String {
+    if value.type_name == "string" {
+        return format!("string({})", value.size);
+    }
+
+    return value.type_name.clone();
+}
+
+// A one character string used as a number is its character
+fn as_char(value: ValueInfo) -> ValueInfo {
+    if value.type_name == "string" {
+        return ValueInfo::new("char".to_string(), 1);
+    }
+
+    return value;
+}
+
+// The wider of two integer types, otherwise the left type
+// When they are the same size signed types are preferred, and arithmetic on characters gives numbers
+fn widest(left: ValueInfo, right: ValueInfo) -> ValueInfo {
+    if integer_size(&left.type_name).is_some() && integer_size(&right.type_name).is_some() && (right.size > left.size || (right.size == left.size && (is_signed(&right.type_name) || left.type_name == "char"))) {
+        return right;
+    }
+
+    return left;
+}
+
+#[derive(Debug, Clone)]
 pub struct Variable {
     pub name: String,
     pub value: ValueInfo
@@ -28,11 +163,11 @@ pub struct Variable {
 
 impl Variable {
     pub fn new(name: String, value: ValueInfo) -> Variable {
-        return Variable { name, value: value };
+        return Variable { name, value };
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Scope {
     pub parent: Option<Box<Scope>>,
     
@@ -42,19 +177,17 @@ pub struct Scope {
 impl Scope {
     pub fn new(parent: Option<Scope>) -> Scope {
         return Scope {
-            parent: match parent { Some(parent) => Some(Box::new(parent)), _ => None },
+            parent: parent.map(Box::new),
             variables: Vec::new()
         };
     }
 
-    pub fn new_child() -> Scope {
-        return Scope {
-            parent: None,
-            variables: Vec::new()
-        };
+    pub fn new_child(self) -> Scope {
+        return Scope::new(Some(self));
     }
 
-    pub fn set(&mut self, name: String, value: ValueInfo) {
+    // Declares the name in this scope, shadowing any variable with the same name further out
+    pub fn define(&mut self, name: String, value: ValueInfo) {
         for variable in self.variables.iter_mut() {
             if variable.name == name {
                 variable.value = value;
@@ -66,70 +199,371 @@ impl Scope {
         self.variables.push(Variable { name, value });
     }
 
+    // Updates the variable in the scope it was declared in, or declares it here
+    pub fn set(&mut self, name: String, value: ValueInfo) {
+        if let Some(variable) = self.get_mut(&name) {
+            variable.value = value;
+
+            return;
+        }
+
+        self.variables.push(Variable { name, value });
+    }
+
     pub fn get(&self, name: &str) -> Option<&Variable> {
-        for variable in self.variables.iter() {
-            if variable.name == name {
-                return Some(variable);
-            }
+        if let Some(variable) = self.variables.iter().find(|variable| variable.name == name) {
+            return Some(variable);
         }
 
-        return None;
+        return self.parent.as_ref().and_then(|parent| parent.get(name));
     }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Variable> {
+        if let Some(variable) = self.variables.iter_mut().find(|variable| variable.name == name) {
+            return Some(variable);
+        }
+
+        return self.parent.as_mut().and_then(|parent| parent.get_mut(name));
+    }
+}
+
+// First expression returned anywhere in the block, used to size a function's result
+fn find_return(block: &[Statement]) -> Option<&Expression> {
+    for stmt in block {
+        if stmt.kind == StatementType::Return {
+            return stmt.expression.as_ref();
+        }
+
+        if stmt.kind == StatementType::Function {
+            continue;
+        }
+
+        for nested in [&stmt.block, &stmt.else_block].into_iter().flatten() {
+            if let Some(expr) = find_return(nested) {
+                return Some(expr);
+            }
+        }
+    }
+
+    return None;
 }
 
 pub struct Analyzer<'a> {
     pub parser: Parser<'a>,
 
-    pub scope: Scope
+    pub scope: Scope,
+
+    // Names declared in blocks that have ended, to explain why they can't be found
+    pub ended: Vec<String>,
+
+    pub functions: HashMap<String, Statement>,
+
+    // Functions whose bodies are being analyzed, a call to one of them is recursion
+    pub calls: Vec<String>,
+
+    // Functions whose declarations are being checked, where names declared later are allowed
+    declaring: Vec<String>,
+
+    // Names a declaration couldn't find, with the function and the error for when it's never called
+    unresolved: Vec<(String, String, CompileError)>,
+
+    // Functions called anywhere, their bodies were checked against the names in scope at the call
+    called: Vec<String>,
+
+    // Everything wrong with the program, checking carries on after an error so they're all found in one pass
+    pub errors: Vec<CompileError>,
+
+    // Things that compile but are likely mistakes, the compiler adds what its simulation finds
+    pub warnings: Vec<CompileError>
 }
 
 impl<'a> Analyzer<'a> {
-    pub fn new(source: &[u8]) -> Analyzer {
+    pub fn new(source: &[u8]) -> Analyzer<'_> {
         return Analyzer {
             parser: Parser::new(Tokenizer::new(source)),
-            scope: Scope::new(None)
+            scope: Scope::new(None),
+            ended: Vec::new(),
+            functions: HashMap::new(),
+            calls: Vec::new(),
+            declaring: Vec::new(),
+            unresolved: Vec::new(),
+            called: Vec::new(),
+            errors: Vec::new(),
+            warnings: Vec::new()
         };
     }
 
+    // Syntax errors are all reported, but the program is only checked once it parses
     pub fn analyze(&mut self) {
-        while self.parser.tokenizer.peek_token().kind != TokenType::EndOfFile {
-            let stmt = self.parser.statement();
+        let program = self.parser.parse();
+
+        // Checking a program with parts missing would only report more errors about them
+        if ! self.parser.errors.is_empty() {
+            self.errors.append(&mut self.parser.errors);
+
+            return;
+        }
+
+        self.warnings = lint(&program);
 
+        for stmt in program {
             self.analyze_stmt(stmt);
         }
+
+        // A function that's never called can only use what's declared by the end of the program
+        for (function, name, error) in std::mem::take(&mut self.unresolved) {
+            if ! self.called.contains(&function) && self.scope.get(&name).is_none() && ! self.functions.contains_key(&name) {
+                self.error(error.span, error.message);
+            }
+        }
+    }
+
+    // Function bodies are analyzed at every call, so the same error can come up more than once
+    pub fn error(&mut self, span: Span, message: String) {
+        if ! self.errors.iter().any(|error| error.span == span && error.message == message) {
+            self.errors.push(CompileError::new(message, span));
+        }
+    }
+
+    // A constant out of range only gets the range error, its type would be a mismatch too
+    fn check_assign(&mut self, target: &ValueInfo, value: &ValueInfo, expr: &Expression, mismatch: String) {
+        if let Some(message) = range_error(&target.type_name, expr) {
+            self.error(expr.span(), message);
+        }
+        else if ! assignable(target, value) {
+            self.error(expr.span(), mismatch);
+        }
+    }
+
+    pub fn enter_scope(&mut self) {
+        let parent = std::mem::replace(&mut self.scope, Scope::new(None));
+
+        self.scope = parent.new_child();
+    }
+
+    pub fn exit_scope(&mut self) -> Scope {
+        let parent = self.scope.parent.take().expect("Can't leave the outermost scope");
+
+        return std::mem::replace(&mut self.scope, *parent);
+    }
+
+    // Leaves a block, remembering what was declared in it
+    pub fn end_block(&mut self) {
+        let scope = self.exit_scope();
+
+        self.ended.extend(scope.variables.into_iter().map(|variable| variable.name));
+    }
+
+    fn undefined_message(&self, name: &str) -> String {
+        if self.ended.iter().any(|ended| ended == name) {
+            return format!("Variable {} is used outside of the block it was declared in", name);
+        }
+
+        return format!("Variable {} not found", name);
+    }
+
+    pub fn undefined(&self, token: &Token) -> CompileError {
+        return CompileError::new(self.undefined_message(&token.string), token.span());
+    }
+
+    // Type of a variable, or Unknown after reporting it missing
+    fn lookup(&mut self, token: &Token) -> ValueInfo {
+        if let Some(variable) = self.scope.get(&token.string) {
+            return variable.value.clone();
+        }
+
+        let error = self.undefined(token);
+
+        self.missing(&token.string, error);
+
+        return ValueInfo::default();
+    }
+
+    // A function body can use names declared after it, as long as they exist where it's called
+    fn missing(&mut self, name: &str, error: CompileError) {
+        match self.declaring.last() {
+            Some(function) => self.unresolved.push((function.clone(), name.to_string(), error)),
+
+            None => self.error(error.span, error.message)
+        }
+    }
+
+    // The type a declaration stores, a string is as long as the value it starts with
+    fn declared(&mut self, type_name: &Token, value: &ValueInfo) -> ValueInfo {
+        if let Some(size) = integer_size(&type_name.string) {
+            return ValueInfo::new(type_name.string.clone(), size);
+        }
+
+        if type_name.string == "string" {
+            return ValueInfo::new("string".to_string(), value.size);
+        }
+
+        self.error(type_name.span(), format!("Unknown type {}", type_name.string));
+
+        return ValueInfo::default();
+    }
+
+    fn condition(&mut self, expr: Expression) {
+        let value = self.analyze_expr(expr.clone());
+
+        if ! numeric(&value) {
+            self.error(expr.span(), format!("Condition must be an integer, not {}", value.type_name));
+        }
+    }
+
+    fn analyze_block(&mut self, block: Vec<Statement>) {
+        self.enter_scope();
+
+        for stmt in block {
+            self.analyze_stmt(stmt);
+        }
+
+        self.end_block();
     }
 
     pub fn analyze_stmt(&mut self, stmt: Statement) {
         match stmt.kind {
+            StatementType::VarDecl if stmt.length.is_some() => {
+                let element = stmt.type_name.as_ref().unwrap();
+
+                if integer_size(&element.string) != Some(1) {
+                    self.error(element.span(), format!("Arrays can only hold single cell values, not {}", element.string));
+                }
+
+                let type_name = format!("{}[]", element.string);
+
+                self.scope.define(stmt.name.unwrap().string, ValueInfo::new(type_name, ArrayWalker::size(stmt.length.unwrap())));
+            }
+
             StatementType::VarDecl => {
                 let expr = stmt.expression.as_ref().unwrap();
 
                 let value = self.analyze_expr(expr.clone());
 
-                self.scope.set(stmt.name.unwrap().string, value);
+                let declared = self.declared(stmt.type_name.as_ref().unwrap(), &value);
+
+                self.check_assign(&declared, &value, expr, format!("Type mismatch: {} doesn't fit in {}", describe(&value), describe(&declared)));
+
+                self.scope.define(stmt.name.unwrap().string, declared);
+            }
+
+            StatementType::Const => {
+                let value = self.analyze_expr(stmt.expression.unwrap());
+
+                self.scope.define(stmt.name.unwrap().string, value);
             }
 
             StatementType::Assignment => {
+                for init in stmt.init.clone().into_iter().flatten() {
+                    self.analyze_stmt(init);
+                }
+
                 let expr = stmt.expression.as_ref().unwrap();
 
                 let value = self.analyze_expr(expr.clone());
 
-                let variable = self.scope.get(&stmt.name.as_ref().unwrap().string);
+                let name = stmt.name.as_ref().unwrap();
+
+                let variable = self.lookup(name);
+
+                if let Some(index) = stmt.index {
+                    if ! variable.type_name.ends_with("[]") && variable.type_name != "Unknown" {
+                        self.error(name.span(), format!("{} isn't an array, it's a {}", name.string, variable.type_name));
+
+                        return;
+                    }
+
+                    let index = self.analyze_expr(index.clone());
+
+                    if ! numeric(&index) {
+                        self.error(name.span(), format!("Index into {} must be an integer, not {}", name.string, index.type_name));
+                    }
+
+                    let element = ValueInfo::new(variable.type_name.trim_end_matches("[]").to_string(), 1);
+
+                    self.check_assign(&element, &value, expr, format!("Type mismatch: {} doesn't fit in an element of {}", describe(&value), variable.type_name));
+
+                    return;
+                }
+
+                if variable.type_name.ends_with("[]") {
+                    self.error(name.span(), format!("Array {} can't be assigned to, assign to its elements instead", name.string));
+
+                    return;
+                }
+
+                self.check_assign(&variable, &value, expr, format!("Type mismatch: {} doesn't fit in {}", describe(&value), describe(&variable)));
+            }
+
+            StatementType::If | StatementType::While => {
+                self.condition(stmt.condition.unwrap());
+
+                self.analyze_block(stmt.block.unwrap());
+
+                if let Some(else_block) = stmt.else_block {
+                    self.analyze_block(else_block);
+                }
+            }
+
+            StatementType::Function => {
+                let types = stmt.params.iter().flatten().map(|(type_name, _)| type_name).chain(stmt.type_name.as_ref());
+
+                for type_name in types.filter(|type_name| ! known_type(&type_name.string)).cloned().collect::<Vec<Token>>() {
+                    self.error(type_name.span(), format!("Unknown type {}", type_name.string));
+                }
+
+                let name = stmt.name.as_ref().unwrap().string.clone();
+
+                self.functions.insert(name.clone(), stmt.clone());
+
+                // Checked once with the declared parameter types, so a function that's never called is checked too
+                // Every call checks it again, since the sizes of strings depend on the arguments
+                self.enter_scope();
 
-                if variable.is_none() {
-                    panic!("Variable {} not found", stmt.name.unwrap().string);
+                for (type_name, param) in stmt.params.iter().flatten() {
+                    let value = match type_name.string.as_str() {
+                        "string" => ValueInfo::default(),
+
+                        _ => self.declared(type_name, &ValueInfo::default())
+                    };
+
+                    self.scope.define(param.string.clone(), value);
                 }
 
-                let variable = variable.unwrap();
+                self.declaring.push(name.clone());
+
+                self.calls.push(name);
+
+                self.body_info(&stmt);
+
+                self.calls.pop();
+
+                self.declaring.pop();
+
+                self.exit_scope();
+            }
+
+            // The loop variables belong to the loop
+            StatementType::For => {
+                self.enter_scope();
 
-                if variable.value.type_name != value.type_name {
-                    // Temporary panic
-                    panic!("Type mismatch: {} != {}", variable.value.type_name, value.type_name);
+                for stmt in stmt.init.unwrap() {
+                    self.analyze_stmt(stmt);
                 }
 
-                // replace variable
+                self.condition(stmt.condition.unwrap());
 
-                self.scope.set(stmt.name.unwrap().string, value);
+                self.analyze_block(stmt.block.unwrap());
+
+                self.analyze_stmt(*stmt.step.unwrap());
+
+                self.end_block();
+            }
+
+            StatementType::Expression | StatementType::Return => {
+                if let Some(expr) = stmt.expression {
+                    self.analyze_expr(expr);
+                }
             }
 
             _ => {
@@ -138,56 +572,504 @@ impl<'a> Analyzer<'a> {
         }
     }
 
+    // Binds the parameters to the argument types and analyzes the body, the bindings go in the current scope so the caller decides how long they live
+    pub fn call_info(&mut self, func: &Statement, args: &[Expression]) -> ValueInfo {
+        let params = func.params.as_ref().unwrap();
+
+        let infos: Vec<ValueInfo> = args.iter().map(|arg| self.analyze_expr(arg.clone())).collect();
+
+        for (((type_name, name), info), arg) in params.iter().zip(infos).zip(args) {
+            let declared = self.declared(type_name, &info);
+
+            self.check_assign(&declared, &info, arg, format!("Type mismatch: {} doesn't fit in {} parameter {}", describe(&info), describe(&declared), name.string));
+
+            self.scope.define(name.string.clone(), declared);
+        }
+
+        return self.body_info(func);
+    }
+
+    // Analyzes the body with the parameters in scope, returning the type the function returns
+    fn body_info(&mut self, func: &Statement) -> ValueInfo {
+        let block = func.block.as_ref().unwrap();
+
+        for stmt in block.clone() {
+            self.analyze_stmt(stmt);
+        }
+
+        let name = func.name.as_ref().unwrap();
+
+        return match (&func.type_name, find_return(block)) {
+            (Some(type_name), Some(expr)) => {
+                let value = self.analyze_expr(expr.clone());
+
+                let declared = self.declared(type_name, &value);
+
+                if ! assignable(&declared, &value) {
+                    self.error(expr.span(), format!("Type mismatch: {} returns {}, not {}", name.string, describe(&declared), describe(&value)));
+                }
+
+                declared
+            },
+
+            (Some(type_name), None) => {
+                self.error(name.span(), format!("{} must return a {}", name.string, type_name.string));
+
+                ValueInfo::default()
+            },
+
+            _ => ValueInfo::new("void".to_string(), 0)
+        };
+    }
+
+    // Builtins are checked against their signature in the table
+    fn builtin_info(&mut self, target: &Token, args: &[Expression], params: &[&str], returns: &str) -> ValueInfo {
+        if params.len() != args.len() {
+            self.error(target.span(), format!("{} expects {} arguments but got {}", target.string, params.len(), args.len()));
+
+            return ValueInfo::default();
+        }
+
+        for (param, arg) in params.iter().zip(args) {
+            let info = self.analyze_expr(arg.clone());
+
+            let fits = match *param {
+                "int" => assignable(&ValueInfo::new("int".to_string(), 1), &info),
+                "integer" => numeric(&info),
+                "string" => info.type_name == "string" || info.type_name == "char",
+                "constant" => arg.kind == ExpressionType::Literal && arg.value.as_ref().unwrap().kind == TokenType::Number,
+                _ => info.type_name != "void"
+            };
+
+            if ! fits && info.type_name != "Unknown" {
+                self.error(arg.span(), format!("{} expects {} as its argument, not {}", target.string, param, info.type_name));
+            }
+        }
+
+        return match returns {
+            "void" => ValueInfo::new("void".to_string(), 0),
+
+            "string" => ValueInfo::new("string".to_string(), literal_value(&args[0]).unwrap_or(0) as usize),
+
+            _ => ValueInfo::new(returns.to_string(), integer_size(returns).unwrap())
+        };
+    }
+
     pub fn analyze_expr(&mut self, expr: Expression) -> ValueInfo {
+        if expr.kind == ExpressionType::Unary {
+            let operator = expr.operator.as_ref().unwrap();
+
+            if operator.kind == TokenType::Minus {
+                let operand = expr.right.as_ref().unwrap();
+
+                // Negative literals are signed, as long as they fit
+                if operand.kind == ExpressionType::Literal && operand.value.as_ref().unwrap().kind == TokenType::Number {
+                    match operand.value.as_ref().unwrap().number {
+                        0..=0x80 => return ValueInfo::new("i8".to_string(), 1),
+                        0x81..=0x8000 => return ValueInfo::new("i16".to_string(), 2),
+                        _ => {}
+                    }
+                }
+            }
+
+            let value = self.analyze_expr(*expr.right.clone().unwrap());
+
+            if ! numeric(&value) {
+                self.error(operator.span(), format!("Operator {} can't be applied to {}", operator.string, value.type_name));
+
+                return ValueInfo::default();
+            }
+
+            if operator.kind == TokenType::Minus {
+                return as_char(value);
+            }
+
+            return ValueInfo::new("int".to_string(), 1);
+        }
+
         if expr.kind == ExpressionType::Binary {
-            let left = self.analyze_expr(expr.left.unwrap().as_ref().clone()).clone();
-            let right = self.analyze_expr(expr.right.unwrap().as_ref().clone()).clone();
+            let operator = expr.operator.as_ref().unwrap();
+
+            let left = self.analyze_expr(expr.left.clone().unwrap().as_ref().clone());
+            let right = self.analyze_expr(expr.right.clone().unwrap().as_ref().clone());
+
+            // Every operator works on integers, characters included
+            if ! numeric(&left) || ! numeric(&right) {
+                self.error(operator.span(), format!("Operator {} can't be applied to {} and {}", operator.string, left.type_name, right.type_name));
+
+                return ValueInfo::default();
+            }
+
+            if matches!(operator.kind, TokenType::And | TokenType::Or | TokenType::EqualEqual | TokenType::NotEqual | TokenType::GT | TokenType::LT | TokenType::GTEqual | TokenType::LTEqual) {
+                return ValueInfo::new("int".to_string(), 1);
+            }
 
-            return ValueInfo::new(left.type_name, left.size);
+            return widest(as_char(left), as_char(right));
         }
 
         if expr.kind == ExpressionType::Call {
             let target = expr.target.as_ref().unwrap();
 
-            // hardcoded for now
-            if target.string == "open" || target.string == "write" {
-                return ValueInfo::new("int".to_string(), 1);
+            let args = expr.args.as_ref().unwrap();
+
+            if let Some((params, returns)) = builtin(&target.string) {
+                return self.builtin_info(target, args, params, returns);
             }
 
-            if target.string == "read" {
-                return ValueInfo::new("string".to_string(), expr.args.unwrap().get(0).as_ref().unwrap().value.as_ref().unwrap().number as usize);
+            let Some(func) = self.functions.get(&target.string).cloned() else {
+                self.missing(&target.string, CompileError::new(format!("Function {} is not defined", target.string), target.span()));
+
+                return ValueInfo::default();
+            };
+
+            self.called.push(target.string.clone());
+
+            let params = func.params.as_ref().unwrap().len();
+
+            if params != args.len() {
+                self.error(target.span(), format!("{} expects {} arguments but got {}", target.string, params, args.len()));
+
+                return ValueInfo::default();
             }
 
-            return ValueInfo::default();
+            // Calls are inlined, so a function can't reach itself again
+            if self.calls.contains(&target.string) {
+                self.error(target.span(), format!("Recursive call to {} can't be inlined", target.string));
+
+                return ValueInfo::default();
+            }
+
+            self.calls.push(target.string.clone());
+
+            self.enter_scope();
+
+            let info = self.call_info(&func, args);
+
+            self.exit_scope();
+
+            self.calls.pop();
+
+            return info;
         }
 
-        if expr.kind == ExpressionType::Literal {
+        if expr.kind == ExpressionType::Index {
             let token = expr.value.as_ref().unwrap();
 
+            let array = self.lookup(token);
+
+            let index = self.analyze_expr(*expr.right.clone().unwrap());
+
+            if ! numeric(&index) {
+                self.error(token.span(), format!("Index into {} must be an integer, not {}", token.string, index.type_name));
+            }
+
+            if ! array.type_name.ends_with("[]") {
+                if array.type_name != "Unknown" {
+                    self.error(token.span(), format!("{} isn't an array, it's a {}", token.string, array.type_name));
+                }
+
+                return ValueInfo::default();
+            }
+
+            return ValueInfo::new(array.type_name.trim_end_matches("[]").to_string(), 1);
+        }
+
+        if expr.kind == ExpressionType::Literal {
+            let token = expr.value.as_ref().unwrap();
 
             match token.kind {
                 TokenType::String => {
-                    return ValueInfo::new("string".to_string(), token.string.len());
+                    return ValueInfo::new("string".to_string(), token.string.chars().count());
                 }
 
                 TokenType::Char => {
                     return ValueInfo::new("char".to_string(), 1);
                 }
 
+                // The smallest type the literal fits in
                 TokenType::Number => {
-                    return ValueInfo::new("int".to_string(), 1);
+                    return match token.number {
+                        0..=0xFF => ValueInfo::new("int".to_string(), 1),
+                        0x100..=0xFFFF => ValueInfo::new("u16".to_string(), 2),
+                        0x10000..=0xFFFFFFFF => ValueInfo::new("u32".to_string(), 4),
+
+                        _ => {
+                            self.error(token.span(), format!("{} doesn't fit in any integer type", token.number));
+
+                            ValueInfo::default()
+                        }
+                    };
                 }
 
                 TokenType::Identifier => {
-                    return self.scope.get(&token.string).unwrap().value.clone();
+                    return self.lookup(token);
                 }
 
                 _ => {
-                    panic!("Unexpected literal type");
+                    self.error(token.span(), format!("Unexpected {:?} in an expression", token.kind));
+
+                    return ValueInfo::default();
                 }
             }
         }
 
         return ValueInfo::default();
     }
-}
\ No newline at end of file
+}
+
+// A variable as the lint pass follows it through the program
+struct Declared {
+    name: String,
+    span: Span,
+    read: bool,
+    // The last value stored in it that nothing has read yet
+    store: Option<Span>
+}
+
+// Finds variables that are never read and values that are overwritten before anything reads them
+#[derive(Default)]
+struct Lint {
+    declared: Vec<Declared>,
+    // Declarations in each block that hasn't ended yet, innermost last
+    scopes: Vec<Vec<usize>>,
+    // Every read in order, so a branch can tell which variables it read
+    reads: Vec<usize>,
+    // Names each function reads that weren't declared where it was defined, read again at every call
+    functions: HashMap<String, Vec<String>>,
+    // Those names for the functions being walked, innermost last
+    free: Vec<Vec<String>>,
+    warnings: Vec<CompileError>
+}
+
+pub fn lint(program: &[Statement]) -> Vec<CompileError> {
+    let mut lint = Lint::default();
+
+    lint.block(program);
+
+    lint.warnings.sort_by_key(|warning| (warning.span.line, warning.span.column));
+
+    return lint.warnings;
+}
+
+// Whether the block can leave early, nested blocks included
+fn leaves(block: &[Statement]) -> bool {
+    return block.iter().any(|stmt| {
+        matches!(stmt.kind, StatementType::Break | StatementType::Continue | StatementType::Return) || [&stmt.block, &stmt.else_block].into_iter().flatten().any(|block| leaves(block))
+    });
+}
+
+impl Lint {
+    fn lookup(&self, name: &str) -> Option<usize> {
+        return self.scopes.iter().rev().flat_map(|scope| scope.iter().rev()).copied().find(|&i| self.declared[i].name == name);
+    }
+
+    fn declare(&mut self, name: &Token, store: Option<Span>, read: bool) {
+        self.declared.push(Declared { name: name.string.clone(), span: name.span(), read, store });
+
+        self.scopes.last_mut().unwrap().push(self.declared.len() - 1);
+    }
+
+    fn read(&mut self, name: &str) {
+        if let Some(i) = self.lookup(name) {
+            self.declared[i].read = true;
+
+            self.declared[i].store = None;
+
+            self.reads.push(i);
+        }
+        else if let Some(free) = self.free.last_mut() {
+            if ! free.iter().any(|free| free == name) {
+                free.push(name.to_string());
+            }
+        }
+    }
+
+    fn store(&mut self, name: &Token) {
+        let Some(i) = self.lookup(&name.string) else {
+            return;
+        };
+
+        if let Some(store) = self.declared[i].store.replace(name.span()) {
+            self.warn(i, store, format!("Value assigned to {} is never read", name.string));
+        }
+    }
+
+    // Names starting with _ are left alone, as are the ones the parser makes up
+    fn warn(&mut self, i: usize, span: Span, message: String) {
+        let name = &self.declared[i].name;
+
+        if ! name.starts_with('_') && ! name.contains('.') {
+            self.warnings.push(CompileError::new(message, span));
+        }
+    }
+
+    fn expr(&mut self, expr: &Expression) {
+        match expr.kind {
+            ExpressionType::Literal if expr.value.as_ref().unwrap().kind == TokenType::Identifier => self.read(&expr.value.as_ref().unwrap().string),
+
+            ExpressionType::Index => self.read(&expr.value.as_ref().unwrap().string),
+
+            // A function can read any variable, so nothing stored before the call is known to be unread
+            ExpressionType::Call if builtin(&expr.target.as_ref().unwrap().string).is_none() => {
+                for declared in &mut self.declared {
+                    declared.store = None;
+                }
+
+                for name in self.functions.get(&expr.target.as_ref().unwrap().string).cloned().into_iter().flatten() {
+                    self.read(&name);
+                }
+            }
+
+            _ => {}
+        }
+
+        for nested in [&expr.left, &expr.right].into_iter().flatten() {
+            self.expr(nested);
+        }
+
+        for arg in expr.args.iter().flatten() {
+            self.expr(arg);
+        }
+    }
+
+    fn block(&mut self, block: &[Statement]) {
+        self.scopes.push(Vec::new());
+
+        for stmt in block {
+            self.stmt(stmt);
+        }
+
+        self.end_block();
+    }
+
+    fn end_block(&mut self) {
+        for i in self.scopes.pop().unwrap() {
+            let declared = &self.declared[i];
+
+            let (name, span, store) = (declared.name.clone(), declared.span, declared.store);
+
+            if ! declared.read {
+                self.warn(i, span, format!("Variable {} is never used", name));
+            }
+            else if let Some(store) = store {
+                self.warn(i, store, format!("Value assigned to {} is never read", name));
+            }
+        }
+    }
+
+    // A block that might not run, or might run more than once
+    // Only what it reads is known afterwards, a value stored before it may still be read when it doesn't run
+    fn branch(&mut self, block: &[Statement], step: Option<&Statement>) {
+        let saved: Vec<Option<Span>> = self.declared.iter_mut().map(|declared| declared.store.take()).collect();
+
+        let start = self.reads.len();
+
+        self.block(block);
+
+        if let Some(step) = step {
+            self.stmt(step);
+        }
+
+        let exits = leaves(block);
+
+        for (i, store) in saved.into_iter().enumerate() {
+            self.declared[i].store = match exits || self.reads[start..].contains(&i) {
+                true => None,
+
+                false => store
+            };
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Statement) {
+        if stmt.doc.is_some() && ! matches!(stmt.kind, StatementType::VarDecl | StatementType::Const | StatementType::Function) {
+            self.warnings.push(CompileError::new("Doc comment is ignored, only variables, constants and functions are documented".to_string(), stmt.span));
+        }
+
+        match stmt.kind {
+            StatementType::VarDecl => {
+                if let Some(expr) = &stmt.expression {
+                    self.expr(expr);
+                }
+
+                let store = stmt.expression.as_ref().map(|_| stmt.name.as_ref().unwrap().span());
+
+                self.declare(stmt.name.as_ref().unwrap(), store, false);
+            }
+
+            // Storing into an element doesn't count as using the array
+            StatementType::Assignment if stmt.index.is_some() => {
+                for init in stmt.init.iter().flatten() {
+                    self.stmt(init);
+                }
+
+                self.expr(stmt.index.as_ref().unwrap());
+
+                self.expr(stmt.expression.as_ref().unwrap());
+            }
+
+            StatementType::Assignment => {
+                self.expr(stmt.expression.as_ref().unwrap());
+
+                self.store(stmt.name.as_ref().unwrap());
+            }
+
+            StatementType::If => {
+                self.expr(stmt.condition.as_ref().unwrap());
+
+                self.branch(stmt.block.as_ref().unwrap(), None);
+
+                if let Some(else_block) = &stmt.else_block {
+                    self.branch(else_block, None);
+                }
+            }
+
+            StatementType::While => {
+                self.expr(stmt.condition.as_ref().unwrap());
+
+                self.branch(stmt.block.as_ref().unwrap(), None);
+            }
+
+            StatementType::For => {
+                self.scopes.push(Vec::new());
+
+                for init in stmt.init.iter().flatten() {
+                    self.stmt(init);
+                }
+
+                self.expr(stmt.condition.as_ref().unwrap());
+
+                self.branch(stmt.block.as_ref().unwrap(), stmt.step.as_deref());
+
+                self.end_block();
+            }
+
+            // Parameters are part of the function's signature, so they aren't expected to all be used
+            StatementType::Function => {
+                self.scopes.push(Vec::new());
+
+                for (_, name) in stmt.params.iter().flatten() {
+                    self.declare(name, None, true);
+                }
+
+                self.free.push(Vec::new());
+
+                self.branch(stmt.block.as_ref().unwrap(), None);
+
+                let free = self.free.pop().unwrap();
+
+                self.functions.insert(stmt.name.as_ref().unwrap().string.clone(), free);
+
+                self.end_block();
+            }
+
+            StatementType::Expression | StatementType::Return => {
+                if let Some(expr) = &stmt.expression {
+                    self.expr(expr);
+                }
+            }
+
+            _ => {}
+        }
+    }
+}
@@ -6,10 +6,12 @@ use super::*;
 
 #[derive(Debug, Clone)]
 pub struct Variable {
-    type_name: String,
-    name: String,
-    expression: Expression,
-	cell: usize
+    pub type_name: String,
+    pub name: String,
+    pub expression: Expression,
+	pub cell: usize,
+	// Element count for arrays, the cell is then the start of the array layout
+	pub length: Option<usize>
 }
 
 impl Variable {
@@ -18,7 +20,8 @@ impl Variable {
             type_name,
             name,
             expression,
-			cell
+			cell,
+			length: None
         }
     }
 }
@@ -71,15 +74,119 @@ impl Scope {
 		return None;
 	}
 
-	pub fn create_child(&mut self) -> Scope {
+	pub fn new_child(self) -> Scope {
 		let mut child = Scope::new();
 
-		child.parent = Some(Box::new(self.clone()));
+		child.parent = Some(Box::new(self));
 
 		return child;
 	}
 }
 
+// Counted for loops with more iterations than this fall back to a runtime loop for the rest
+const MAX_UNROLL: usize = 32;
+
+// Names of every variable assigned in the block, including nested blocks
+fn assigned(block: &[Statement]) -> Vec<String> {
+	let mut names = Vec::new();
+
+	for stmt in block {
+		if stmt.kind == StatementType::Assignment {
+			names.push(stmt.name.as_ref().unwrap().string.clone());
+		}
+
+		for nested in [&stmt.block, &stmt.else_block, &stmt.init].into_iter().flatten() {
+			names.extend(assigned(nested));
+		}
+
+		if let Some(step) = &stmt.step {
+			names.extend(assigned(std::slice::from_ref(step.as_ref())));
+		}
+	}
+
+	return names;
+}
+
+// Whether any statement in the block, including nested blocks, assigns to the variable
+fn assigns(block: &[Statement], name: &str) -> bool {
+	return assigned(block).iter().any(|assigned| assigned == name);
+}
+
+// Whether the statement can leave its block early, so the statements after it need a guard
+fn may_exit(stmt: &Statement) -> bool {
+	if matches!(stmt.kind, StatementType::Return | StatementType::Break | StatementType::Continue) {
+		return true;
+	}
+
+	if stmt.kind == StatementType::Function {
+		return false;
+	}
+
+	// Loops handle their own break and continue, only a return leaves the enclosing block
+	if matches!(stmt.kind, StatementType::While | StatementType::For) {
+		return stmt.block.as_ref().unwrap().iter().any(may_return);
+	}
+
+	return [&stmt.block, &stmt.else_block].into_iter().flatten().any(|block| block.iter().any(may_exit));
+}
+
+fn may_return(stmt: &Statement) -> bool {
+	if stmt.kind == StatementType::Return {
+		return true;
+	}
+
+	if stmt.kind == StatementType::Function {
+		return false;
+	}
+
+	return [&stmt.block, &stmt.else_block].into_iter().flatten().any(|block| block.iter().any(may_return));
+}
+
+// Whether the statement breaks out of or continues the loop it's in, nested loops don't count
+fn may_break(stmt: &Statement) -> bool {
+	if matches!(stmt.kind, StatementType::Break | StatementType::Continue) {
+		return true;
+	}
+
+	if matches!(stmt.kind, StatementType::While | StatementType::For | StatementType::Function) {
+		return false;
+	}
+
+	return [&stmt.block, &stmt.else_block].into_iter().flatten().any(|block| block.iter().any(may_break));
+}
+
+// Lets the simulation warn when arithmetic on values it knows doesn't fit, integers only
+fn overflow_lint(operator: &Token, a: usize, b: usize, size: usize, left: &ValueInfo, right: &ValueInfo) -> Option<Lint> {
+	if integer_size(&left.type_name).is_none() || integer_size(&right.type_name).is_none() {
+		return None;
+	}
+
+	let signed = is_signed(&left.type_name) || is_signed(&right.type_name);
+
+	return Some(Lint::Overflow { a, b, size, signed, operator: operator.string.clone(), span: operator.span() });
+}
+
+// Where control goes when a block is left early
+#[derive(Debug, Clone)]
+pub enum Exit {
+	// Result cells owned by the caller and a flag that is cleared once the function returns
+	Function { result: usize, size: usize, running: usize },
+
+	// Running is cleared by break to end the loop, active is cleared by break and continue to skip the rest of the iteration
+	Loop { running: usize, active: usize }
+}
+
+impl Exit {
+	// Cell that has to be set for the rest of the current block to run
+	pub fn guard(&self) -> usize {
+		return match self {
+			Exit::Function { running, .. } => *running,
+
+			Exit::Loop { active, .. } => *active
+		};
+	}
+}
+
 pub struct Compiler<'a> {
 	pub gen: Generator,
 
@@ -91,11 +198,21 @@ pub struct Compiler<'a> {
 
 	pub analyzer: Analyzer<'a>,
 
-	pub last_return: ValueInfo
+	pub last_return: ValueInfo,
+
+	pub functions: HashMap<String, Statement>,
+
+	// Values of constants, which are substituted wherever they're used instead of taking cells
+	pub constants: HashMap<String, u64>,
+
+	// Functions currently being inlined, used to reject recursion
+	pub calls: Vec<String>,
+
+	pub exits: Vec<Exit>
 }
 
 impl<'a> Compiler<'a> {
-	pub fn new(source: &[u8]) -> Compiler {
+	pub fn new(source: &[u8]) -> Compiler<'_> {
 		return Compiler {
 			gen: Generator::new(),
 
@@ -107,141 +224,150 @@ impl<'a> Compiler<'a> {
 
 			analyzer: Analyzer::new(source),
 
-			last_return: ValueInfo::default()
+			last_return: ValueInfo::default(),
+
+			functions: HashMap::new(),
+
+			constants: HashMap::new(),
+
+			calls: Vec::new(),
+
+			exits: Vec::new()
 		};
 	}
 
-	pub fn compile_expression(&mut self, branch: &mut BFBlock, expr: Expression) -> usize {
+	pub fn compile_expression(&mut self, branch: &mut BFBlock, expr: Expression) -> CompileResult<usize> {
 		Debug::log(&format!("Compiling expression: {}", expr.stringify()));
+
+		let span = expr.span();
 		
-		if expr.kind == ExpressionType::Binary {
-			match expr.operator.as_ref().unwrap().kind {
-				TokenType::EqualEqual => {
-					let left = self.compile_expression(branch, *expr.left.unwrap());
+		if expr.kind == ExpressionType::Unary {
+			if expr.operator.as_ref().unwrap().kind == TokenType::Not {
+				let operand = self.compile_condition(branch, *expr.right.unwrap())?;
 
-					let right = self.compile_expression(branch, *expr.right.unwrap());
+				branch.add(BoolNegate::new(operand, self.gen.memory.alloc(1)));
 
-					let sub = Sub::new(left, right);
+				return Ok(operand);
+			}
 
-					let negate = BoolNegate::new(sub.a, self.gen.memory.alloc(1));
+			let info = self.analyzer.analyze_expr(*expr.right.clone().unwrap());
 
-					let result = negate.a;
+			let operand = self.compile_expression(branch, *expr.right.unwrap())?;
 
-					branch.add(sub);
+			match expr.operator.as_ref().unwrap().kind {
+				TokenType::Minus if integer_size(&info.type_name).is_some() && info.size > 1 => {
+					let zero = self.gen.memory.alloc(info.size);
 
-					branch.add(negate);
+					for i in 0..info.size {
+						branch.add(Set::new(zero + i, 0));
+					}
+
+					branch.add(WideSub::new(zero, operand, info.size, self.gen.memory.alloc(2)));
 
-					return result;
+					return Ok(zero);
 				},
 
-				TokenType::NotEqual => {
-					let left = self.compile_expression(branch, *expr.left.unwrap());
+				// -x is compiled as 0 - x
+				TokenType::Minus => {
+					let zero = self.gen.memory.alloc(1);
 
-					let right = self.compile_expression(branch, *expr.right.unwrap());
+					branch.add(Set::new(zero, 0));
 
-					let sub = Sub::new(left, right);
+					branch.add(Sub::new(zero, operand));
 
-					// self.output += &dst.compile(&mut self.gen);
+					return Ok(zero);
+				},
 
-					// dst.simulate(&mut self.gen);
-					/*self.output += &sum.compile(&mut self.gen);
+				_ => return Err(CompileError::new("Unsupported unary operator".to_string(), span))
+			}
+		}
 
-					sum.simulate(&mut self.gen);*/
+		if expr.kind == ExpressionType::Binary {
+			let left = self.analyzer.analyze_expr(*expr.left.clone().unwrap());
 
-					let result = sub.a;
+			let right = self.analyzer.analyze_expr(*expr.right.clone().unwrap());
 
-					branch.add(sub);
+			let logic = matches!(expr.operator.as_ref().unwrap().kind, TokenType::And | TokenType::Or);
 
-					return result;
-				}
+			if ! logic && integer_size(&left.type_name).is_some() && integer_size(&right.type_name).is_some() {
+				let signed = is_signed(&left.type_name) || is_signed(&right.type_name);
 
-				TokenType::GT | TokenType::GTEqual => {
-					let left = self.compile_expression(branch, *expr.left.unwrap());
+				let operator = expr.operator.as_ref().unwrap().kind.clone();
 
-					let right = self.compile_expression(branch, *expr.right.unwrap());
+				if signed && matches!(operator, TokenType::Slash | TokenType::Mod) {
+					return self.compile_signed_div(branch, expr, &left, &right);
+				}
 
-					let mut dst = Distance::new(left, right);
+				// Ordering comparisons go through the wide compare, which orders single cells too and can bias the sign bit of signed ones
+				if left.size.max(right.size) > 1 || matches!(operator, TokenType::GT | TokenType::LT | TokenType::GTEqual | TokenType::LTEqual) {
+					return self.compile_wide(branch, expr, &left, &right);
+				}
+			}
 
-					// Result
-					let gt = dst.gt(&mut self.gen);
+			let (left_info, right_info) = (left, right);
 
-					// This might not be a good long term solution, consider using BoolNegate if this fails
-					if expr.operator.unwrap().kind != TokenType::GTEqual {
-						// Hack because it acts like >= otherwise for some reason
-						let tmp = self.gen.memory.alloc(1);
-	
-						branch.add(Set::new(tmp, 1));
-	
-						branch.add(Sub::new(left, tmp));
+			match expr.operator.as_ref().unwrap().kind {
+				TokenType::And | TokenType::Or => {
+					let left = self.compile_condition(branch, *expr.left.unwrap())?;
 
-						branch.add(dst);
+					let mut logic = Logic::new(expr.operator.unwrap().kind == TokenType::Or, left, self.gen.memory.alloc(1));
 
-						return gt;
-					}
-					
-					let lt = dst.lt(&mut self.gen);
+					// The right side is compiled into the instruction so it can be skipped
+					logic.b = self.compile_condition(&mut logic.right, *expr.right.unwrap())?;
 
-					// If not greater, make the cell a 1 and add it to the result so the condition is true if equal
-					let negate = BoolNegate::new(lt, self.gen.memory.alloc(1));
+					branch.add(logic);
 
-					let na = negate.a;
+					return Ok(left);
+				},
 
-					branch.add(dst);
+				TokenType::EqualEqual => {
+					let left = self.compile_expression(branch, *expr.left.unwrap())?;
 
-					branch.add(negate);
+					let right = self.compile_expression(branch, *expr.right.unwrap())?;
 
-					// Add other distance to test if equal
-					branch.add(Add::new(gt, na));
+					let sub = Sub::new(left, right);
 
-					return gt;
-				}
+					let negate = BoolNegate::new(sub.a, self.gen.memory.alloc(1));
 
-				TokenType::LT | TokenType::LTEqual => {
-					let left = self.compile_expression(branch, *expr.left.unwrap());
+					let result = negate.a;
 
-					let right = self.compile_expression(branch, *expr.right.unwrap());
+					branch.add(sub);
 
-					let mut dst = Distance::new(left, right);
+					branch.add(negate);
 
-					// Result
-					let lt = dst.lt(&mut self.gen);
+					return Ok(result);
+				},
 
-					// This might not be a good long term solution, consider using BoolNegate if this fails
-					if expr.operator.unwrap().kind != TokenType::LTEqual {
-						// Hack because it acts like <= otherwise for some reason
-						let tmp = self.gen.memory.alloc(1);
-	
-						branch.add(Set::new(tmp, 1));
-	
-						branch.add(Sub::new(right, tmp));
+				TokenType::NotEqual => {
+					let left = self.compile_expression(branch, *expr.left.unwrap())?;
 
-						branch.add(dst);
+					let right = self.compile_expression(branch, *expr.right.unwrap())?;
 
-						return lt;
-					}
-				
-					let gt = dst.gt(&mut self.gen);
+					let sub = Sub::new(left, right);
 
-					// If not greater, make the cell a 1 and add it to the result so the condition is true if equal
-					let negate = BoolNegate::new(gt, self.gen.memory.alloc(1));
+					let result = sub.a;
 
-					let na = negate.a;
+					branch.add(sub);
 
-					branch.add(dst);
+					// The difference is turned into 0 or 1 like every other comparison
+					let tmp = self.gen.memory.alloc(1);
 
-					branch.add(negate);
+					branch.add(BoolNegate::new(result, tmp));
 
-					// Add other distance to test if equal
-					branch.add(Add::new(lt, na));	
+					branch.add(BoolNegate::new(result, tmp));
 
-					return lt;
+					return Ok(result);
 				}
-				
+
 				TokenType::Plus => {
-					let left = self.compile_expression(branch, *expr.left.unwrap());
+					let left = self.compile_expression(branch, *expr.left.unwrap())?;
 
-					let right = self.compile_expression(branch, *expr.right.unwrap());
+					let right = self.compile_expression(branch, *expr.right.unwrap())?;
 					
+					if let Some(lint) = overflow_lint(expr.operator.as_ref().unwrap(), left, right, 1, &left_info, &right_info) {
+						branch.add(lint);
+					}
+
 					let add = Add::new(left, right);
 
 					/*self.output += &add.compile(&mut self.gen);
@@ -250,14 +376,18 @@ impl<'a> Compiler<'a> {
 
 					branch.add(add);
 
-					return left;
+					return Ok(left);
 				},
 
 				TokenType::Minus => {
-					let left = self.compile_expression(branch, *expr.left.unwrap());
+					let left = self.compile_expression(branch, *expr.left.unwrap())?;
 
-					let right = self.compile_expression(branch, *expr.right.unwrap());
+					let right = self.compile_expression(branch, *expr.right.unwrap())?;
 					
+					if let Some(lint) = overflow_lint(expr.operator.as_ref().unwrap(), left, right, 1, &left_info, &right_info) {
+						branch.add(lint);
+					}
+
 					let sub = Sub::new(left, right);
 
 					/*self.output += &sub.compile(&mut self.gen);
@@ -266,14 +396,18 @@ impl<'a> Compiler<'a> {
 
 					branch.add(sub);
 
-					return left;
+					return Ok(left);
 				},
 
 				TokenType::Times => {
-					let left = self.compile_expression(branch, *expr.left.unwrap());
+					let left = self.compile_expression(branch, *expr.left.unwrap())?;
 
-					let right = self.compile_expression(branch, *expr.right.unwrap());
+					let right = self.compile_expression(branch, *expr.right.unwrap())?;
 					
+					if let Some(lint) = overflow_lint(expr.operator.as_ref().unwrap(), left, right, 1, &left_info, &right_info) {
+						branch.add(lint);
+					}
+
 					let mut mul = Mul::new(left, right);
 
 					/*self.output += &mul.compile(&mut self.gen);
@@ -284,16 +418,14 @@ impl<'a> Compiler<'a> {
 
 					branch.add(mul);
 
-					return result;
+					return Ok(result);
 				},
 
 				TokenType::Slash => {
-					let left = self.compile_expression(branch, *expr.left.unwrap());
+					let left = self.compile_expression(branch, *expr.left.unwrap())?;
 
-					let right = self.compile_expression(branch, *expr.right.unwrap());
+					let right = self.compile_expression(branch, *expr.right.unwrap())?;
 
-					println!("Current {}", self.gen.memory.current);
-					
 					let mut div = Div::new(left, right);
 
 					let result = div.result(&mut self.gen);
@@ -306,34 +438,51 @@ impl<'a> Compiler<'a> {
 
 					// div.clean(&mut self.gen);
 
-					return result;
+					return Ok(result);
+				},
+
+				TokenType::Mod => {
+					let left = self.compile_expression(branch, *expr.left.unwrap())?;
+
+					let right = self.compile_expression(branch, *expr.right.unwrap())?;
+
+					let mut div = Div::new(left, right);
+
+					let result = div.remainder(&mut self.gen);
+
+					branch.add(div);
+
+					return Ok(result);
 				},
 
-				_ => panic!("Not implemented!")
+				_ => return Err(CompileError::new("Unsupported binary operator".to_string(), span))
 			}
 		}
 		
 		if expr.kind == ExpressionType::Literal {
 			if expr.value.as_ref().unwrap().kind == TokenType::Number {
-				let loc = self.gen.memory.alloc(1);
+				let number = expr.value.as_ref().unwrap().number;
 
-				Debug::log(&format!("Number literal parsed: ({})", expr.value.as_ref().unwrap().number));
+				// Literals take as many cells as their type, lowest cell first
+				let size = self.analyzer.analyze_expr(expr.clone()).size;
 
-				let set = Set::new(loc, expr.value.as_ref().unwrap().number as CellSize);
+				let loc = self.gen.memory.alloc(size);
 
-				/*self.output += &set.compile(&mut self.gen);
+				Debug::log(&format!("Number literal parsed: ({})", number));
 
-				set.simulate(&mut self.gen);*/
+				for i in 0..size {
+					let set = Set::new(loc + i, ((number >> (8 * i)) & 0xFF) as CellSize);
 
-				branch.add(set);
+					branch.add(set);
+				}
 
-				return loc;
+				return Ok(loc);
 			}
 
 			if expr.value.as_ref().unwrap().kind == TokenType::String {
 				let value = expr.value.as_ref().unwrap().string.clone();
 
-				let loc = self.gen.memory.alloc(value.len() + 1);
+				let loc = self.gen.memory.alloc(value.chars().count() + 1);
 
 				Debug::log(&format!("String literal parsed: ({})", expr.value.as_ref().unwrap().string));
 
@@ -343,11 +492,11 @@ impl<'a> Compiler<'a> {
 					branch.add(set);
 				}
 
-				let set = Set::new(loc + value.len(), 0);
+				let set = Set::new(loc + value.chars().count(), 0);
 
 				branch.add(set);
 
-				return loc;
+				return Ok(loc);
 			}
 
 			if expr.value.as_ref().unwrap().kind == TokenType::Char {
@@ -363,21 +512,38 @@ impl<'a> Compiler<'a> {
 
 				branch.add(set);
 
-				return loc;
+				return Ok(loc);
 			}
 
 			if expr.value.as_ref().unwrap().kind == TokenType::Identifier {
 				let token = expr.value.as_ref().unwrap();
 
+				// Constants are set like literals
+				if let Some(&value) = self.constants.get(&token.string) {
+					let size = self.analyzer.scope.get(&token.string).unwrap().value.size;
+
+					let loc = self.gen.memory.alloc(size);
+
+					for i in 0..size {
+						branch.add(Set::new(loc + i, ((value >> (8 * i)) & 0xFF) as CellSize));
+					}
+
+					return Ok(loc);
+				}
+
 				let var = self.scope.get(&token.string);
 
 				if var.is_none() {
-					panic!("Variable not defined!");
+					return Err(self.analyzer.undefined(token));
+				}
+
+				if var.unwrap().length.is_some() {
+					return Err(CompileError::new(format!("Array {} can't be used as a value, index it with []", token.string), span));
 				}
 
 				/*let mut updated = var.unwrap().clone();
 
-				updated.cell = self.compile_expression(branch, updated.expression.clone());
+				updated.cell = self.compile_expression(branch, updated.expression.clone())?;
 				
 				self.scope.define(&updated);*/
 
@@ -395,16 +561,25 @@ impl<'a> Compiler<'a> {
 
 				self.gen.memory.free(tmp);
 
-				return cell;
+				return Ok(cell);
 			}
 		}
 
 		if expr.kind == ExpressionType::Call {
 			if expr.target.as_ref().unwrap().string == "print" {
-				let arg = self.compile_expression(branch, expr.args.as_ref().unwrap().get(0).unwrap().clone());
+				let arg = self.compile_expression(branch, expr.args.as_ref().unwrap().get(0).unwrap().clone())?;
 
 				let info = self.analyzer.analyze_expr(expr.args.as_ref().unwrap().get(0).unwrap().clone());
 
+				// Strings can end early at runtime, so they're printed up to their terminator
+				if info.type_name == "string" {
+					branch.add(PrintString::new(arg, info.size, self.gen.memory.alloc(PrintString::size(info.size))));
+
+					self.last_return = ValueInfo::new("void".to_string(), 0);
+
+					return Ok(arg);
+				}
+
 				for i in 0..info.size {
 					let output = Output::new(arg + i);
 				
@@ -413,11 +588,115 @@ impl<'a> Compiler<'a> {
 
 				self.last_return = ValueInfo::new("void".to_string(), 0);
 
-				return arg;
+				return Ok(arg);
+			}
+
+			if expr.target.as_ref().unwrap().string == "print_int" {
+				let argexpr = expr.args.as_ref().unwrap().get(0).unwrap().clone();
+
+				let info = self.analyzer.analyze_expr(argexpr.clone());
+
+				let arg = self.compile_expression(branch, argexpr)?;
+
+				let size = info.size.max(1);
+
+				// Negative numbers print a minus sign and then their magnitude
+				if is_signed(&info.type_name) {
+					let negative = self.sign(branch, arg + size - 1);
+
+					let minus = self.gen.memory.alloc(1);
+
+					let mut check = If::new(negative);
+
+					check.block.add(Set::new(minus, b'-' as CellSize));
+
+					check.block.add(Output::new(minus));
+
+					check.block.add(Clear::new(minus));
+
+					branch.add(check);
+
+					let negative = self.sign(branch, arg + size - 1);
+
+					self.negate_if(branch, arg, size, negative);
+				}
+
+				branch.add(PrintInt::new(arg, size, self.gen.memory.alloc(PrintInt::size(size))));
+
+				self.last_return = ValueInfo::new("void".to_string(), 0);
+
+				return Ok(arg);
+			}
+
+			if expr.target.as_ref().unwrap().string == "read_int" {
+				let cell = self.gen.memory.alloc(1);
+
+				branch.add(ReadInt::new(cell, self.gen.memory.alloc(ReadInt::SIZE)));
+
+				self.last_return = ValueInfo::new("int".to_string(), 1);
+
+				return Ok(cell);
+			}
+
+			if expr.target.as_ref().unwrap().string == "read_line" {
+				if expr.args.as_ref().unwrap().first().is_none_or(|arg| arg.value.as_ref().is_none_or(|value| value.kind != TokenType::Number)) {
+					return Err(CompileError::new("Argument to read_line() must be a constant".to_string(), span));
+				}
+
+				let max = expr.args.as_ref().unwrap().get(0).unwrap().value.as_ref().unwrap().number as usize;
+
+				let cell = self.gen.memory.alloc(max + 1);
+
+				for i in 0..=max {
+					branch.add(Set::new(cell + i, 0));
+				}
+
+				let reading = self.gen.memory.alloc(1);
+
+				let (tmp, condition, stop, end) = (self.gen.memory.alloc(1), self.gen.memory.alloc(1), self.gen.memory.alloc(1), self.gen.memory.alloc(1));
+
+				branch.add(Set::new(reading, 1));
+
+				// Each cell is only read while no newline or end of input has been seen, which are both left as zero
+				for i in 0..max {
+					branch.add(Copy::new(condition, tmp, reading));
+
+					let mut read = If::new(condition);
+
+					read.block.add(Input::new(cell + i));
+
+					read.block.add(Copy::new(stop, tmp, cell + i));
+
+					read.block.add(Offset::new(stop, 256 - b'\n' as CellSize));
+
+					read.block.add(BoolNegate::new(stop, tmp));
+
+					read.block.add(Copy::new(end, tmp, cell + i));
+
+					read.block.add(BoolNegate::new(end, tmp));
+
+					read.block.add(Add::new(stop, end));
+
+					let mut finish = If::new(stop);
+
+					finish.block.add(Clear::new(cell + i));
+
+					finish.block.add(Clear::new(reading));
+
+					read.block.add(finish);
+
+					branch.add(read);
+				}
+
+				self.last_return = ValueInfo::new("string".to_string(), max);
+
+				return Ok(cell);
 			}
 
 			if expr.target.as_ref().unwrap().string == "read" {
-				assert!(expr.args.as_ref().unwrap().get(0).unwrap().value.as_ref().unwrap().kind == TokenType::Number, "Argument to read() must be a constant");
+				if expr.args.as_ref().unwrap().first().is_none_or(|arg| arg.value.as_ref().is_none_or(|value| value.kind != TokenType::Number)) {
+					return Err(CompileError::new("Argument to read() must be a constant".to_string(), span));
+				}
 				
 				let amount = expr.args.as_ref().unwrap().get(0).unwrap().value.as_ref().unwrap().number;
 
@@ -435,23 +714,27 @@ impl<'a> Compiler<'a> {
 
 				self.last_return = ValueInfo::new("string".to_string(), amount as usize);
 
-				return cell;
+				return Ok(cell);
 			}
 
 			if expr.target.as_ref().unwrap().string == "open" {
 				let expr = expr.args.as_ref().unwrap().get(0).unwrap();
 
-				let arg = self.compile_expression(branch, expr.clone());
+				let arg = self.compile_expression(branch, expr.clone())?;
 
 				let len = match expr.kind {
 					ExpressionType::Literal => {
 						match expr.value.as_ref().unwrap().kind {
 							TokenType::String => {
-								expr.value.as_ref().unwrap().string.len()
+								expr.value.as_ref().unwrap().string.chars().count()
 							},
 
 							TokenType::Identifier => {
-								self.analyzer.scope.get(&expr.value.as_ref().unwrap().string).unwrap_or_else(|| { panic!("Undefined variable {}", expr.value.as_ref().unwrap().string) }).value.size
+								match self.analyzer.scope.get(&expr.value.as_ref().unwrap().string) {
+									Some(variable) => variable.value.size,
+
+									None => return Err(self.analyzer.undefined(expr.value.as_ref().unwrap()))
+								}
 							}
 
 							_ => 1
@@ -483,23 +766,27 @@ impl<'a> Compiler<'a> {
 
 				self.last_return = ValueInfo::new("int".to_string(), 1);
 
-				return op;
+				return Ok(op);
 			}
 
 			if expr.target.as_ref().unwrap().string == "write" {
 				let argexpr = expr.args.as_ref().unwrap().get(1).unwrap();
 
-				let data = self.compile_expression(branch, argexpr.clone());
+				let data = self.compile_expression(branch, argexpr.clone())?;
 
 				let len = match argexpr.kind {
 					ExpressionType::Literal => {
 						match argexpr.value.as_ref().unwrap().kind {
 							TokenType::String => {
-								argexpr.value.as_ref().unwrap().string.len() + 1
+								argexpr.value.as_ref().unwrap().string.chars().count() + 1
 							},
 
 							TokenType::Identifier => {
-								self.analyzer.scope.get(&argexpr.value.as_ref().unwrap().string).unwrap_or_else(|| { panic!("Undefined variable {}", argexpr.value.as_ref().unwrap().string) }).value.size
+								match self.analyzer.scope.get(&argexpr.value.as_ref().unwrap().string) {
+									Some(variable) => variable.value.size,
+
+									None => return Err(self.analyzer.undefined(argexpr.value.as_ref().unwrap()))
+								}
 							},
 
 							_ => 1
@@ -518,14 +805,14 @@ impl<'a> Compiler<'a> {
 				};
 
 				if len == 0 {
-					panic!("Empty argument passed to write");
+					return Err(CompileError::new("Empty argument passed to write".to_string(), argexpr.span()));
 				}
 
 				let op = self.gen.memory.alloc(len + 2);
 
 				branch.add(Set::new(op, ExtendedBF::Write as CellSize));
 
-				let handle = self.compile_expression(branch, expr.args.as_ref().unwrap().get(0).unwrap().clone());
+				let handle = self.compile_expression(branch, expr.args.as_ref().unwrap().get(0).unwrap().clone())?;
 
 				let tmp = self.gen.memory.alloc(1);
 
@@ -543,110 +830,1005 @@ impl<'a> Compiler<'a> {
 
 				self.last_return = ValueInfo::new("int".to_string(), 1);
 
-				return op;
+				return Ok(op);
 			}
 		}
 
-		panic!("Not implemented!");
-	}
+		if expr.kind == ExpressionType::Index {
+			let (base, length) = self.array(expr.value.as_ref().unwrap())?;
 
-	pub fn compile_statement(&mut self, branch: &mut BFBlock, stmt: Statement) {
-		if stmt.kind == StatementType::If {
-			let condition = self.compile_expression(branch, stmt.condition.unwrap());
+			let result = self.gen.memory.alloc(1);
 
-			let mut check = If::new(condition);
+			let index = *expr.right.unwrap();
 
-			let current = self.gen.clone();
+			let span = index.span();
 
-			// check.block.
-			
-			for stmt in stmt.block.unwrap() {
-				self.compile_statement(&mut check.block, stmt);
+			// Constant indices resolve to a cell right away
+			if let Some(k) = self.constant_index(&index, length)? {
+				branch.add(Copy::new(result, base + 1, ArrayWalker::element(base, k)));
+
+				return Ok(result);
 			}
 
-			branch.add(check);
+			let cell = self.compile_expression(branch, index)?;
 
-			return;
-		}
+			branch.add(ArrayWalker::read(base, length, cell, result, span));
 
-		if stmt.kind == StatementType::While {
-			let condition = self.compile_expression(branch, stmt.condition.clone().unwrap());
+			return Ok(result);
+		}
 
-			let mut check = While::new(condition);
+		if expr.kind == ExpressionType::Call {
+			let name = expr.target.as_ref().unwrap().string.clone();
 
-			let current = self.gen.clone();
+			if self.calls.contains(&name) {
+				return Err(CompileError::new(format!("Recursive call to {} can't be inlined", name), span));
+			}
 
-			// check.block.
-			
-			for stmt in stmt.block.unwrap() {
-				self.compile_statement(&mut check.block, stmt);
+			if let Some(func) = self.functions.get(&name).cloned() {
+				return self.compile_call(branch, func, expr.args.unwrap());
 			}
-			
-			let newcondition = self.compile_expression(&mut check.block, stmt.condition.clone().unwrap());
 
-			check.block.add(Move::new(condition, newcondition));
+			return Err(CompileError::new(format!("Function {} is not defined", name), span));
+		}
 
-			branch.add(check);
+		return Err(CompileError::new("Unsupported expression".to_string(), span));
+	}
+
+	// Calls are inlined, the body runs in a child scope and writes its return value into a cell owned by the caller
+	pub fn compile_call(&mut self, branch: &mut BFBlock, func: Statement, args: Vec<Expression>) -> CompileResult<usize> {
+		let name = func.name.as_ref().unwrap().string.clone();
 
-			return;
+		if let Some(doc) = &func.doc {
+			branch.add(Comment::new(&format!("{}: {}", name, doc)));
 		}
 
-		if stmt.kind == StatementType::VarDecl {
-			let expr = stmt.expression.unwrap();
+		// Arguments are typed and compiled before the parameters can shadow anything they use
+		let arg_infos: Vec<ValueInfo> = args.iter().map(|arg| self.analyzer.analyze_expr(arg.clone())).collect();
 
-			let cell = self.compile_expression(branch, expr.clone());
+		let cells = args.iter().map(|arg| self.compile_expression(branch, arg.clone())).collect::<CompileResult<Vec<usize>>>()?;
 
-			Debug::log(&format!("Defined {} as {}", stmt.name.as_ref().unwrap().string, cell));
-			
-			self.scope.define(&Variable::new(stmt.type_name.unwrap().string, stmt.name.unwrap().string, expr, cell));
+		self.enter_block();
 
-			return;
-		}
+		let info = self.analyzer.call_info(&func, &args);
 
-		if stmt.kind == StatementType::Assignment {
-			let expr = stmt.expression.unwrap();
+		let result = self.gen.memory.alloc(info.size.max(1));
 
-			let cell = self.compile_expression(branch, expr.clone());
-			
-			let var = self.scope.get_mut(&stmt.name.as_ref().unwrap().string).unwrap();
+		for ((((type_name, param), arg), arg_info), mut cell) in func.params.unwrap().into_iter().zip(args).zip(arg_infos).zip(cells) {
+			if integer_size(&arg_info.type_name).is_some() && type_name.string != "string" {
+				let size = self.analyzer.scope.get(&param.string).unwrap().value.size;
 
-			Debug::log(&format!("Assigning {}({}) = {}", stmt.name.as_ref().unwrap().string, var.cell, cell));
-			
-			let mv = Move::new(var.cell, cell);
+				cell = self.widen(branch, cell, &arg_info, size);
+			}
 
-			branch.add(mv);
+			if type_name.string == "string" && arg_info.type_name == "char" {
+				cell = self.char_string(branch, cell);
+			}
 
-			return;
+			self.scope.define(&Variable::new(type_name.string, param.string, arg, cell));
 		}
 
-		if stmt.kind == StatementType::Expression {
-			self.compile_expression(branch, stmt.expression.unwrap());
+		let running = self.gen.memory.alloc(1);
 
-			return;
-		}
-	}
+		branch.add(Set::new(running, 1));
 
-	pub fn compile(&mut self) -> String {
-		// let mut str = String::new();
+		self.calls.push(name);
 
-		self.analyzer.analyze();
+		self.exits.push(Exit::Function { result, size: info.size, running });
 
-		let mut branch = BFBlock::new();
+		self.compile_block(branch, func.block.unwrap())?;
 
-		while self.parser.tokenizer.peek_token().kind != TokenType::EndOfFile {
-			let stmt = self.parser.statement();
-			
-			self.compile_statement(&mut branch, stmt);
+		self.exits.pop();
+
+		self.calls.pop();
+
+		// The parameters go out of scope with the call
+		self.exit_block();
+
+		self.last_return = info;
+
+		return Ok(result);
+	}
+
+	// Runs an expression on a scratch generator, the same way the real one would simulate it
+	// Nothing is emitted and no cells are taken, so it has to be known without running the program
+	pub fn evaluate(&mut self, expr: Expression, name: &str) -> CompileResult<u64> {
+		if let Some(variable) = self.variable_in(&expr) {
+			return Err(CompileError::new(format!("Constant {} can't use the variable {}, only literals and other constants", name, variable), expr.span()));
 		}
 
-		let mut str = String::new();
+		let span = expr.span();
 
-		for mut instr in branch.instructions {
-			str += &instr.compile(&mut self.gen);
+		let size = self.analyzer.analyze_expr(expr.clone()).size;
+
+		let mut scratch = Generator::new();
+
+		scratch.lints = self.gen.lints.clone();
+
+		let saved = std::mem::replace(&mut self.gen, scratch);
+
+		let mut block = BFBlock::new();
+
+		// The real generator has to come back even when compiling fails
+		let cell = match self.compile_expression(&mut block, expr) {
+			Ok(cell) => cell,
+
+			Err(error) => {
+				self.gen = saved;
+
+				return Err(error);
+			}
+		};
+
+		for instr in &mut block.instructions {
+			instr.compile(&mut self.gen);
 
 			instr.simulate(&mut self.gen);
 		}
 
-		return str;
+		let scratch = std::mem::replace(&mut self.gen, saved);
+
+		if scratch.memory.any_dirty(cell, size) {
+			return Err(CompileError::new(format!("Constant {} depends on input, so it can't be evaluated at compile time", name), span));
+		}
+
+		return Ok(scratch.memory.get_int(cell, size));
+	}
+
+	// First variable an expression reads, constants don't count
+	fn variable_in(&self, expr: &Expression) -> Option<String> {
+		if matches!(expr.kind, ExpressionType::Literal | ExpressionType::Index) {
+			let token = expr.value.as_ref().unwrap();
+
+			if (token.kind == TokenType::Identifier && ! self.constants.contains_key(&token.string)) || expr.kind == ExpressionType::Index {
+				return Some(token.string.clone());
+			}
+		}
+
+		let args = expr.args.iter().flatten();
+
+		return [&expr.left, &expr.right].into_iter().flatten().map(|expr| &**expr).chain(args).find_map(|expr| self.variable_in(expr));
+	}
+
+	// Copies an integer into a wider block of cells, the new upper cells are zero or copies of the sign bit
+	pub fn widen(&mut self, branch: &mut BFBlock, cell: usize, from: &ValueInfo, to: usize) -> usize {
+		if from.size >= to {
+			return cell;
+		}
+
+		let wide = self.gen.memory.alloc(to);
+
+		for i in 0..from.size {
+			branch.add(Move::new(wide + i, cell + i));
+		}
+
+		for i in from.size..to {
+			branch.add(Set::new(wide + i, 0));
+		}
+
+		if is_signed(&from.type_name) {
+			let negative = self.sign(branch, wide + from.size - 1);
+
+			let mut extend = If::new(negative);
+
+			for i in from.size..to {
+				extend.block.add(Set::new(wide + i, 0xFF));
+			}
+
+			branch.add(extend);
+		}
+
+		return wide;
+	}
+
+	// A new cell that is set when the highest bit of a cell is, leaving the cell itself alone
+	pub fn sign(&mut self, branch: &mut BFBlock, cell: usize) -> usize {
+		let copy = self.gen.memory.alloc(1);
+
+		let limit = self.gen.memory.alloc(1);
+
+		let tmp = self.gen.memory.alloc(4);
+
+		let lt = self.gen.memory.alloc(1);
+
+		let eq = self.gen.memory.alloc(1);
+
+		branch.add(Copy::new(copy, tmp, cell));
+
+		branch.add(Set::new(limit, 0x80));
+
+		branch.add(WideCompare::new(copy, limit, 1, tmp, lt, eq));
+
+		branch.add(BoolNegate::new(lt, tmp));
+
+		return lt;
+	}
+
+	// Same as sign, but unsigned integers are never negative
+	fn negative(&mut self, branch: &mut BFBlock, cell: usize, info: &ValueInfo) -> usize {
+		if is_signed(&info.type_name) {
+			return self.sign(branch, cell);
+		}
+
+		let zero = self.gen.memory.alloc(1);
+
+		branch.add(Set::new(zero, 0));
+
+		return zero;
+	}
+
+	// Negates a signed integer in place when the condition cell is set, the condition is consumed
+	fn negate_if(&mut self, branch: &mut BFBlock, cell: usize, size: usize, condition: usize) {
+		let zero = self.gen.memory.alloc(size);
+
+		let mut negate = If::new(condition);
+
+		for i in 0..size {
+			negate.block.add(Set::new(zero + i, 0));
+		}
+
+		if size == 1 {
+			negate.block.add(Sub::new(zero, cell));
+		}
+		else {
+			negate.block.add(WideSub::new(zero, cell, size, self.gen.memory.alloc(2)));
+		}
+
+		for i in 0..size {
+			negate.block.add(Move::new(cell + i, zero + i));
+		}
+
+		branch.add(negate);
+	}
+
+	// A character becomes a one character string
+	fn char_string(&mut self, branch: &mut BFBlock, cell: usize) -> usize {
+		let string = self.gen.memory.alloc(2);
+
+		branch.add(Move::new(string, cell));
+
+		branch.add(Set::new(string + 1, 0));
+
+		return string;
+	}
+
+	// Copies a cell into a new one, keeping the original
+	fn duplicate(&mut self, branch: &mut BFBlock, cell: usize) -> usize {
+		let copy = self.gen.memory.alloc(1);
+
+		branch.add(Copy::new(copy, self.gen.memory.alloc(1), cell));
+
+		return copy;
+	}
+
+	// Divides the magnitudes and fixes the signs afterwards, the quotient rounds towards zero and the remainder takes the sign of the dividend
+	pub fn compile_signed_div(&mut self, branch: &mut BFBlock, expr: Expression, left: &ValueInfo, right: &ValueInfo) -> CompileResult<usize> {
+		if left.size > 1 || right.size > 1 {
+			return Err(CompileError::new("Division is only supported on single cell integers".to_string(), expr.operator.as_ref().unwrap().span()));
+		}
+
+		let operator = expr.operator.unwrap().kind;
+
+		let a = self.compile_expression(branch, *expr.left.unwrap())?;
+
+		let b = self.compile_expression(branch, *expr.right.unwrap())?;
+
+		let sa = self.negative(branch, a, left);
+
+		let sb = self.negative(branch, b, right);
+
+		let condition = self.duplicate(branch, sa);
+
+		self.negate_if(branch, a, 1, condition);
+
+		let condition = self.duplicate(branch, sb);
+
+		self.negate_if(branch, b, 1, condition);
+
+		let mut div = Div::new(a, b);
+
+		let result = match operator {
+			TokenType::Slash => div.result(&mut self.gen),
+			_ => div.remainder(&mut self.gen)
+		};
+
+		branch.add(div);
+
+		// The quotient is negative when exactly one side was, the difference of the signs is only set then
+		let condition = match operator {
+			TokenType::Slash => {
+				branch.add(Sub::new(sa, sb));
+
+				sa
+			},
+
+			_ => sa
+		};
+
+		self.negate_if(branch, result, 1, condition);
+
+		return Ok(result);
+	}
+
+	// Conditions only look at a single cell, so wider integers are folded into one that is set when any of their cells are
+	pub fn compile_condition(&mut self, branch: &mut BFBlock, expr: Expression) -> CompileResult<usize> {
+		let info = self.analyzer.analyze_expr(expr.clone());
+
+		let cell = self.compile_expression(branch, expr)?;
+
+		if integer_size(&info.type_name).is_none() || info.size <= 1 {
+			return Ok(cell);
+		}
+
+		let tmp = self.gen.memory.alloc(1);
+
+		for i in 0..info.size {
+			branch.add(BoolNegate::new(cell + i, tmp));
+
+			branch.add(BoolNegate::new(cell + i, tmp));
+		}
+
+		for i in 1..info.size {
+			branch.add(Add::new(cell, cell + i));
+		}
+
+		return Ok(cell);
+	}
+
+	// Binary operators on integers wider than a cell, both sides are widened to the same size first
+	pub fn compile_wide(&mut self, branch: &mut BFBlock, expr: Expression, left_info: &ValueInfo, right_info: &ValueInfo) -> CompileResult<usize> {
+		let token = expr.operator.unwrap();
+
+		let span = token.span();
+
+		let operator = token.kind.clone();
+
+		let size = left_info.size.max(right_info.size);
+
+		let left = self.compile_expression(branch, *expr.left.unwrap())?;
+
+		let left = self.widen(branch, left, left_info, size);
+
+		let right = self.compile_expression(branch, *expr.right.unwrap())?;
+
+		let right = self.widen(branch, right, right_info, size);
+
+		let tmp = self.gen.memory.alloc(4);
+
+		// Flipping the sign bit orders two's complement numbers the same way as unsigned ones
+		if (is_signed(&left_info.type_name) || is_signed(&right_info.type_name)) && matches!(operator, TokenType::GT | TokenType::LT | TokenType::GTEqual | TokenType::LTEqual) {
+			branch.add(Offset::new(left + size - 1, 0x80));
+
+			branch.add(Offset::new(right + size - 1, 0x80));
+		}
+
+		if matches!(operator, TokenType::Plus | TokenType::Minus | TokenType::Times) {
+			if let Some(lint) = overflow_lint(&token, left, right, size, left_info, right_info) {
+				branch.add(lint);
+			}
+		}
+
+		match operator {
+			TokenType::Plus => {
+				branch.add(WideAdd::new(left, right, size, tmp));
+
+				return Ok(left);
+			},
+
+			TokenType::Minus => {
+				branch.add(WideSub::new(left, right, size, tmp));
+
+				return Ok(left);
+			},
+
+			TokenType::Times => {
+				let result = self.gen.memory.alloc(size);
+
+				branch.add(WideMul::new(left, right, size, result, tmp));
+
+				return Ok(result);
+			},
+
+			TokenType::EqualEqual | TokenType::NotEqual | TokenType::GT | TokenType::LT | TokenType::GTEqual | TokenType::LTEqual => {
+				let lt = self.gen.memory.alloc(1);
+
+				let eq = self.gen.memory.alloc(1);
+
+				// a > b is b < a
+				let (a, b) = match operator {
+					TokenType::GT | TokenType::LTEqual => (right, left),
+
+					_ => (left, right)
+				};
+
+				branch.add(WideCompare::new(a, b, size, tmp, lt, eq));
+
+				let result = match operator {
+					TokenType::EqualEqual | TokenType::NotEqual => eq,
+
+					_ => lt
+				};
+
+				// The remaining comparisons are the inverse of one above
+				if matches!(operator, TokenType::NotEqual | TokenType::GTEqual | TokenType::LTEqual) {
+					branch.add(BoolNegate::new(result, tmp));
+				}
+
+				return Ok(result);
+			},
+
+			TokenType::Slash | TokenType::Mod => return Err(CompileError::new("Division is only supported on single cell integers".to_string(), span)),
+
+			_ => return Err(CompileError::new("Unsupported operator".to_string(), span))
+		}
+	}
+
+	// Start of the array layout and its element count
+	pub fn array(&self, name: &Token) -> CompileResult<(usize, usize)> {
+		let Some(var) = self.scope.get(&name.string) else {
+			return Err(self.analyzer.undefined(name));
+		};
+
+		let Some(length) = var.length else {
+			return Err(CompileError::new(format!("{} is not an array", name.string), name.span()));
+		};
+
+		return Ok((var.cell, length));
+	}
+
+	// Number literal indices are checked against the length at compile time
+	pub fn constant_index(&mut self, index: &Expression, length: usize) -> CompileResult<Option<usize>> {
+		let k = match index.value.as_ref() {
+			Some(token) if index.kind == ExpressionType::Literal && token.kind == TokenType::Number => token.number,
+
+			// Anything else made only of literals and constants, like 2 + 3 or 'a', is worked out here too
+			_ if self.variable_in(index).is_none() && ! has_call(index) => self.evaluate(index.clone(), "index")? as i64,
+
+			_ => return Ok(None)
+		};
+
+		if k < 0 || k as usize >= length {
+			return Err(CompileError::new(format!("Index {} is out of bounds for an array of length {}", k, length), index.span()));
+		}
+
+		return Ok(Some(k as usize));
+	}
+
+	pub fn enter_block(&mut self) {
+		let parent = std::mem::replace(&mut self.scope, Scope::new());
+
+		self.scope = parent.new_child();
+
+		self.analyzer.enter_scope();
+	}
+
+	// Cells of variables declared in the block are returned to the pool, the code using them has already been generated
+	pub fn exit_block(&mut self) {
+		let parent = self.scope.parent.take().expect("Can't leave the outermost scope");
+
+		let scope = std::mem::replace(&mut self.scope, *parent);
+
+		for var in scope.variables.values() {
+			let size = match var.length {
+				Some(length) => ArrayWalker::size(length),
+
+				None => self.analyzer.scope.get(&var.name).map_or(1, |info| info.value.size)
+			};
+
+			for i in 0..size {
+				self.gen.memory.free(var.cell + i);
+			}
+		}
+
+		// Constants declared in the block end with it as well, parameters can't be constants
+		for variable in &self.analyzer.scope.variables {
+			if ! scope.variables.contains_key(&variable.name) {
+				self.constants.remove(&variable.name);
+			}
+		}
+
+		self.analyzer.end_block();
+	}
+
+	pub fn compile_block(&mut self, branch: &mut BFBlock, block: Vec<Statement>) -> CompileResult<()> {
+		self.enter_block();
+
+		self.compile_statements(branch, block)?;
+
+		self.exit_block();
+
+		return Ok(());
+	}
+
+	// Compiles statements in order, once a statement may have exited the rest only runs while the exit's guard is still set
+	fn compile_statements(&mut self, branch: &mut BFBlock, block: Vec<Statement>) -> CompileResult<()> {
+		let mut stmts = block.into_iter();
+
+		while let Some(stmt) = stmts.next() {
+			let exits = may_exit(&stmt);
+
+			let returns = matches!(stmt.kind, StatementType::Return | StatementType::Break | StatementType::Continue);
+
+			self.compile_statement(branch, stmt)?;
+
+			// Anything after an unconditional return, break or continue can never run
+			if returns {
+				return Ok(());
+			}
+
+			if exits && stmts.len() > 0 {
+				let guard = self.exits.last().unwrap().guard();
+
+				let cond = self.gen.memory.alloc(1);
+
+				let tmp = self.gen.memory.alloc(1);
+
+				branch.add(Copy::new(cond, tmp, guard));
+
+				let mut check = If::new(cond);
+
+				self.compile_statements(&mut check.block, stmts.collect())?;
+
+				branch.add(check);
+
+				return Ok(());
+			}
+		}
+
+		return Ok(());
+	}
+
+	pub fn compile_statement(&mut self, branch: &mut BFBlock, mut stmt: Statement) -> CompileResult<()> {
+		if stmt.kind == StatementType::Assignment {
+			for init in stmt.init.take().into_iter().flatten() {
+				self.compile_statement(branch, init)?;
+			}
+		}
+
+		if let (Some(name), true) = (&stmt.name, matches!(stmt.kind, StatementType::VarDecl | StatementType::Const | StatementType::Assignment)) {
+			if self.constants.contains_key(&name.string) {
+				return Err(CompileError::new(format!("{} is a constant and can't be declared or assigned again", name.string), name.span()));
+			}
+		}
+
+		// The analyzer follows along, so it knows what's in scope at this point of the program
+		if matches!(stmt.kind, StatementType::VarDecl | StatementType::Const | StatementType::Assignment) {
+			let declared = stmt.clone();
+
+			self.compile_declaration(branch, stmt)?;
+
+			self.analyzer.analyze_stmt(declared);
+
+			return Ok(());
+		}
+
+		if stmt.kind == StatementType::If {
+			let span = stmt.condition.as_ref().unwrap().span();
+
+			let condition = self.compile_condition(branch, stmt.condition.unwrap())?;
+
+			branch.add(Lint::Condition { cell: condition, has_else: stmt.else_block.is_some(), span });
+
+			if let Some(else_block) = stmt.else_block {
+				let mut check = IfElse::new(condition, self.gen.memory.alloc(1));
+
+				self.compile_block(&mut check.block, stmt.block.unwrap())?;
+
+				self.compile_block(&mut check.else_block, else_block)?;
+
+				branch.add(check);
+
+				return Ok(());
+			}
+
+			let mut check = If::new(condition);
+
+			self.compile_block(&mut check.block, stmt.block.unwrap())?;
+
+			branch.add(check);
+
+			return Ok(());
+		}
+
+		if stmt.kind == StatementType::While {
+			self.compile_while(branch, stmt.condition.unwrap(), stmt.block.unwrap(), None)?;
+
+			return Ok(());
+		}
+
+		// The loop variables only live as long as the loop
+		if stmt.kind == StatementType::For {
+			self.enter_block();
+
+			self.compile_for(branch, stmt)?;
+
+			self.exit_block();
+
+			return Ok(());
+		}
+
+		if stmt.kind == StatementType::Function {
+			self.functions.insert(stmt.name.as_ref().unwrap().string.clone(), stmt);
+
+			return Ok(());
+		}
+
+		if stmt.kind == StatementType::Return {
+			let mut function = None;
+
+			// Returning from inside a loop also ends every loop between here and the function
+			for exit in self.exits.iter().rev() {
+				match *exit {
+					Exit::Loop { running, active } => {
+						branch.add(Set::new(running, 0));
+
+						branch.add(Set::new(active, 0));
+					},
+
+					Exit::Function { result, size, running } => {
+						function = Some((result, size, running));
+
+						break;
+					}
+				}
+			}
+
+			let Some((result, size, running)) = function else {
+				return Err(CompileError::new("Return outside of a function".to_string(), stmt.span));
+			};
+
+			if let Some(expr) = stmt.expression {
+				let info = self.analyzer.analyze_expr(expr.clone());
+
+				let mut cell = self.compile_expression(branch, expr)?;
+
+				if integer_size(&info.type_name).is_some() {
+					cell = self.widen(branch, cell, &info, size);
+				}
+
+				for i in 0..size {
+					branch.add(Move::new(result + i, cell + i));
+				}
+			}
+
+			branch.add(Set::new(running, 0));
+
+			return Ok(());
+		}
+
+		if stmt.kind == StatementType::Break || stmt.kind == StatementType::Continue {
+			let Some(&Exit::Loop { running, active }) = self.exits.last() else {
+				return Err(CompileError::new(format!("{:?} outside of a loop", stmt.kind), stmt.span));
+			};
+
+			if stmt.kind == StatementType::Break {
+				branch.add(Set::new(running, 0));
+			}
+
+			branch.add(Set::new(active, 0));
+
+			return Ok(());
+		}
+
+		if stmt.kind == StatementType::Expression {
+			self.compile_expression(branch, stmt.expression.unwrap())?;
+
+			return Ok(());
+		}
+
+		return Ok(());
+	}
+
+	// Variable and constant declarations and assignments
+	fn compile_declaration(&mut self, branch: &mut BFBlock, stmt: Statement) -> CompileResult<()> {
+		if stmt.kind == StatementType::Const {
+			// A constant takes no cells, so its doc comment goes where it's declared
+			if let Some(doc) = &stmt.doc {
+				branch.add(Comment::new(doc));
+			}
+
+			let value = self.evaluate(stmt.expression.unwrap(), &stmt.name.as_ref().unwrap().string)?;
+
+			self.constants.insert(stmt.name.unwrap().string, value);
+
+			return Ok(());
+		}
+
+		// Functions are documented where they're inlined instead
+		if let (StatementType::VarDecl, Some(doc)) = (&stmt.kind, &stmt.doc) {
+			branch.add(Comment::new(doc));
+		}
+
+		if let (StatementType::VarDecl, Some(length)) = (&stmt.kind, stmt.length) {
+			let cell = self.gen.memory.alloc(ArrayWalker::size(length));
+
+			// The walker relies on every marker and carry cell starting out cleared
+			for i in 0..ArrayWalker::size(length) {
+				branch.add(Set::new(cell + i, 0));
+			}
+
+			let mut var = Variable::new(stmt.type_name.unwrap().string, stmt.name.unwrap().string, Expression::default(), cell);
+
+			var.length = Some(length);
+
+			self.scope.define(&var);
+
+			return Ok(());
+		}
+
+		if stmt.kind == StatementType::VarDecl {
+			let expr = stmt.expression.unwrap();
+
+			let info = self.analyzer.analyze_expr(expr.clone());
+
+			let mut cell = self.compile_expression(branch, expr.clone())?;
+
+			if let (Some(size), Some(_)) = (integer_size(&stmt.type_name.as_ref().unwrap().string), integer_size(&info.type_name)) {
+				cell = self.widen(branch, cell, &info, size);
+			}
+
+			if stmt.type_name.as_ref().unwrap().string == "string" && info.type_name == "char" {
+				cell = self.char_string(branch, cell);
+			}
+
+			Debug::log(&format!("Defined {} as {}", stmt.name.as_ref().unwrap().string, cell));
+			
+			self.scope.define(&Variable::new(stmt.type_name.unwrap().string, stmt.name.unwrap().string, expr, cell));
+
+			return Ok(());
+		}
+
+		if let (StatementType::Assignment, Some(index)) = (&stmt.kind, stmt.index.clone()) {
+			let (base, length) = self.array(stmt.name.as_ref().unwrap())?;
+
+			let value = self.compile_expression(branch, stmt.expression.unwrap())?;
+
+			if let Some(k) = self.constant_index(&index, length)? {
+				branch.add(Move::new(ArrayWalker::element(base, k), value));
+
+				return Ok(());
+			}
+
+			let span = index.span();
+
+			let cell = self.compile_expression(branch, index)?;
+
+			branch.add(ArrayWalker::write(base, length, cell, value, span));
+
+			return Ok(());
+		}
+
+		if stmt.kind == StatementType::Assignment {
+			let expr = stmt.expression.unwrap();
+
+			if self.scope.get(&stmt.name.as_ref().unwrap().string).is_some_and(|var| var.length.is_some()) {
+				return Err(CompileError::new(format!("Array {} can't be assigned to, assign to its elements instead", stmt.name.as_ref().unwrap().string), stmt.span));
+			}
+
+			let info = self.analyzer.analyze_expr(expr.clone());
+
+			let target = self.analyzer.scope.get(&stmt.name.as_ref().unwrap().string).unwrap().value.clone();
+
+			let mut size = info.size.max(1);
+
+			let mut cell = self.compile_expression(branch, expr.clone())?;
+
+			// Integers are widened to the size of the variable
+			if integer_size(&target.type_name).is_some() {
+				size = target.size;
+
+				cell = self.widen(branch, cell, &info, size);
+			}
+			
+			let var = self.scope.get_mut(&stmt.name.as_ref().unwrap().string).unwrap();
+
+			Debug::log(&format!("Assigning {}({}) = {}", stmt.name.as_ref().unwrap().string, var.cell, cell));
+			
+			for i in 0..size {
+				branch.add(Move::new(var.cell + i, cell + i));
+			}
+
+			// A shorter string ends early, so the rest of the variable is cleared
+			for i in size..target.size {
+				branch.add(Clear::new(var.cell + i));
+			}
+
+			return Ok(());
+		}
+
+		return Ok(());
+	}
+
+	fn compile_for(&mut self, branch: &mut BFBlock, stmt: Statement) -> CompileResult<()> {
+		for init in stmt.init.unwrap() {
+			self.compile_statement(branch, init)?;
+		}
+
+		let mut block = stmt.block.unwrap();
+
+		// The step still has to run after a continue, so it stays out of the guarded body
+		if block.iter().any(|stmt| may_break(stmt) || may_return(stmt)) {
+			self.compile_while(branch, stmt.condition.unwrap(), block, stmt.step.map(|step| *step))?;
+
+			return Ok(());
+		}
+
+		block.push(*stmt.step.unwrap());
+
+		let single = |name: &str| self.analyzer.scope.get(name).is_some_and(|var| var.value.size == 1);
+
+		let counter = match &stmt.name {
+			Some(name) if ! assigns(&block[..block.len() - 1], &name.string) && single(&name.string) && single(&format!("{}.end", name.string)) => Some(name.string.clone()),
+
+			_ => None
+		};
+
+		// Counted loops keep the While lowering as a fallback for when the bounds are only known at runtime
+		if let Some(counter) = counter {
+			let cell = self.scope.get(&counter).unwrap().cell;
+
+			let end = self.scope.get(&format!("{}.end", counter)).unwrap().cell;
+
+			let mut unroll = Unroll::new(cell, end, MAX_UNROLL);
+
+			self.compile_block(&mut unroll.block, block.clone())?;
+
+			self.compile_while(&mut unroll.fallback, stmt.condition.unwrap(), block, None)?;
+
+			branch.add(unroll);
+
+			return Ok(());
+		}
+
+		self.compile_while(branch, stmt.condition.unwrap(), block, None)?;
+
+		return Ok(());
+	}
+
+	pub fn compile_while(&mut self, branch: &mut BFBlock, condition: Expression, block: Vec<Statement>, step: Option<Statement>) -> CompileResult<()> {
+		let cell = self.compile_condition(branch, condition.clone())?;
+
+		branch.add(Lint::Condition { cell, has_else: false, span: condition.span() });
+
+		// A return inside the loop ends it the same way a break does
+		let breaks = block.iter().any(|stmt| may_break(stmt) || may_return(stmt));
+
+		// The body is only simulated once, so anything it changes can't be treated as known
+		let mut names = assigned(&block);
+
+		names.extend(assigned(step.as_slice()));
+
+		let mut cells = Vec::new();
+
+		for name in names {
+			if let Some(var) = self.scope.get(&name) {
+				let size = self.analyzer.scope.get(&name).map_or(1, |info| info.value.size);
+
+				cells.extend(var.cell..var.cell + size);
+			}
+		}
+
+		let mut check = While::new(cell);
+
+		if ! breaks {
+			branch.add(Runtime::new(cells));
+
+			self.compile_block(&mut check.block, block)?;
+
+			if let Some(step) = step {
+				self.compile_statement(&mut check.block, step)?;
+			}
+
+			let newcondition = self.compile_condition(&mut check.block, condition)?;
+
+			check.block.add(Move::new(cell, newcondition));
+
+			branch.add(check);
+
+			return Ok(());
+		}
+
+		let running = self.gen.memory.alloc(1);
+
+		let active = self.gen.memory.alloc(1);
+
+		branch.add(Set::new(running, 1));
+
+		cells.extend([running, active]);
+
+		branch.add(Runtime::new(cells));
+
+		check.block.add(Set::new(active, 1));
+
+		self.exits.push(Exit::Loop { running, active });
+
+		self.compile_block(&mut check.block, block)?;
+
+		self.exits.pop();
+
+		let tmp = self.gen.memory.alloc(1);
+
+		// A break skips the step
+		if let Some(step) = step {
+			let cond = self.gen.memory.alloc(1);
+
+			check.block.add(Copy::new(cond, tmp, running));
+
+			let mut guard = If::new(cond);
+
+			self.compile_statement(&mut guard.block, step)?;
+
+			check.block.add(guard);
+		}
+
+		let newcondition = self.compile_condition(&mut check.block, condition)?;
+
+		check.block.add(Move::new(cell, newcondition));
+
+		// The loop only continues while running is still set
+		let stop = self.gen.memory.alloc(1);
+
+		check.block.add(Copy::new(stop, tmp, running));
+
+		check.block.add(BoolNegate::new(stop, tmp));
+
+		let mut end = If::new(stop);
+
+		end.block.add(Clear::new(cell));
+
+		check.block.add(end);
+
+		branch.add(check);
+
+		return Ok(());
+	}
+
+	// Every error found is returned, type errors are all found before compiling starts
+	pub fn compile(&mut self) -> Result<String, Vec<CompileError>> {
+		// let mut str = String::new();
+
+		self.analyzer.analyze();
+
+		if ! self.analyzer.errors.is_empty() {
+			return Err(std::mem::take(&mut self.analyzer.errors));
+		}
+
+		// Compiling declares everything again as it's reached
+		self.analyzer.scope = bfsanalyzer::Scope::new(None);
+
+		let mut branch = BFBlock::new();
+
+		let program = self.parser.parse();
+
+		if ! self.parser.errors.is_empty() {
+			return Err(std::mem::take(&mut self.parser.errors));
+		}
+
+		for stmt in program {
+			self.compile_statement(&mut branch, stmt).map_err(|error| vec![error])?;
+		}
+
+		let mut str = String::new();
+
+		for mut instr in branch.instructions {
+			str += &instr.compile(&mut self.gen);
+
+			instr.simulate(&mut self.gen);
+		}
+
+		// Indices the simulation knows are out of bounds
+		let errors = std::mem::take(&mut self.gen.lints.borrow_mut().errors);
+
+		if ! errors.is_empty() {
+			return Err(errors);
+		}
+
+		// Warnings from the simulation join the analyzer's, in the order they appear in the source
+		let lints = self.gen.lints.borrow().collect();
+
+		self.analyzer.warnings.extend(lints);
+
+		self.analyzer.warnings.sort_by_key(|warning| (warning.span.line, warning.span.column));
+
+		return Ok(str);
 	}
 }
\ No newline at end of file
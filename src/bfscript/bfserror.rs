@@ -0,0 +1,66 @@
+use std::fmt;
+
+// Where something is in the source, lines and columns start at 1
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+	pub line: u32,
+
+	pub column: u32
+}
+
+impl Span {
+	pub fn new(line: u32, column: u32) -> Span {
+		return Span { line, column };
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct CompileError {
+	pub message: String,
+
+	pub span: Span
+}
+
+pub type CompileResult<T> = Result<T, CompileError>;
+
+impl CompileError {
+	pub fn new(message: String, span: Span) -> CompileError {
+		return CompileError { message, span };
+	}
+
+	// Shows the offending line with a caret under the column, the same way rustc does
+	pub fn render(&self, source: &[u8], file: &str) -> String {
+		return self.render_as("error", source, file);
+	}
+
+	// Same as render, labelled as a warning or anything else
+	pub fn render_as(&self, level: &str, source: &[u8], file: &str) -> String {
+		let text = String::from_utf8_lossy(source);
+
+		let line = text.lines().nth(self.span.line.saturating_sub(1) as usize).unwrap_or("");
+
+		let number = self.span.line.to_string();
+
+		let gutter = " ".repeat(number.len());
+
+		// Tabs are kept so the caret lines up however wide they're shown
+		let indent: String = line.chars().take(self.span.column.saturating_sub(1) as usize).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+
+		let mut rendered = format!("{}: {}\n", level, self.message);
+
+		rendered += &format!("{}--> {}:{}:{}\n", gutter, file, self.span.line, self.span.column);
+		rendered += &format!("{} |\n", gutter);
+		rendered += &format!("{} | {}\n", number, line);
+		rendered += &format!("{} | {}^\n", gutter, indent);
+
+		return rendered;
+	}
+}
+
+impl fmt::Display for CompileError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return write!(f, "{}:{}: {}", self.span.line, self.span.column, self.message);
+	}
+}
+
+impl std::error::Error for CompileError {}
@@ -3,6 +3,7 @@
 use super::Tokenizer;
 use super::Token;
 use super::TokenType;
+use super::{CompileError, CompileResult, Span};
 
 #[derive(Debug, PartialEq, Clone, Default)]
 pub enum ExpressionType {
@@ -11,6 +12,7 @@ pub enum ExpressionType {
 	Unary,
 	Literal,
 	Call,
+	Index,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -48,6 +50,15 @@ impl Expression {
 		};
 	}
 
+	pub fn new_unary(operator: Token, right: Expression) -> Expression {
+		return Expression {
+			kind: ExpressionType::Unary,
+			operator: Some(operator),
+			right: Some(Box::new(right)),
+			..Default::default()
+		};
+	}
+
 	pub fn new_literal(value: Token) -> Expression {
 		return Expression {
 			kind: ExpressionType::Literal,
@@ -65,6 +76,25 @@ impl Expression {
 		};
 	}
 
+	// Element of an array, the value is the array name and right is the index
+	pub fn new_index(value: Token, index: Expression) -> Expression {
+		return Expression {
+			kind: ExpressionType::Index,
+			value: Some(value),
+			right: Some(Box::new(index)),
+			..Default::default()
+		};
+	}
+
+	// Where the expression starts, its leftmost token
+	pub fn span(&self) -> Span {
+		if let Some(left) = &self.left {
+			return left.span();
+		}
+
+		return self.value.as_ref().or(self.target.as_ref()).or(self.operator.as_ref()).map_or(Span::default(), Token::span);
+	}
+
 	pub fn stringify(&self) -> String {
 		let mut str = String::new();
 		
@@ -79,6 +109,13 @@ impl Expression {
 				return str;
 			},
 
+			ExpressionType::Unary => {
+				str += &self.operator.as_ref().unwrap().string;
+				str += &self.right.as_ref().unwrap().stringify();
+
+				return str;
+			},
+
 			ExpressionType::Literal => {
 				let value = self.value.as_ref().unwrap();
 
@@ -109,7 +146,12 @@ impl Expression {
 				return str;
 			},
 
-			_ => {
+			ExpressionType::Index => {
+				str += &self.value.as_ref().unwrap().string;
+				str += "[";
+				str += &self.right.as_ref().unwrap().stringify();
+				str += "]";
+
 				return str;
 			}
 		}
@@ -120,9 +162,14 @@ impl Expression {
 pub enum StatementType {
 	If,
 	While,
+	For,
 	VarDecl,
+	Const,
 	Assignment,
+	Function,
 	Return,
+	Break,
+	Continue,
 	Expression
 }
 
@@ -138,7 +185,30 @@ pub struct Statement {
 
 	pub block: Option<Vec<Statement>>,
 
-	pub expression: Option<Expression>
+	pub else_block: Option<Vec<Statement>>,
+
+	// Only used by for loops, init runs once before the loop and step after every iteration
+	// An assignment runs init before anything else too
+	pub init: Option<Vec<Statement>>,
+
+	pub step: Option<Box<Statement>>,
+
+	// Function parameters as (type, name) pairs
+	pub params: Option<Vec<(Token, Token)>>,
+
+	// Element count of an array declaration
+	pub length: Option<usize>,
+
+	// Element an assignment writes to when the target is an array
+	pub index: Option<Expression>,
+
+	// Text of the /// comments before the statement, only declarations use it
+	pub doc: Option<String>,
+
+	pub expression: Option<Expression>,
+
+	// Where the statement starts
+	pub span: Span
 }
 
 impl Statement {
@@ -147,35 +217,189 @@ impl Statement {
 			kind,
 			condition: None,
 			block: None,
+			else_block: None,
+			init: None,
+			step: None,
+			params: None,
+			length: None,
+			index: None,
+			doc: None,
 			expression: None,
 		    name: None,
 		    type_name: None,
+			span: Span::default()
 		}
 	}
 }
 
+// Whether the expression calls anything, which can't be worked out at compile time
+pub fn has_call(expr: &Expression) -> bool {
+	if expr.kind == ExpressionType::Call {
+		return true;
+	}
+
+	return [&expr.left, &expr.right].into_iter().flatten().any(|expr| has_call(expr));
+}
+
 pub struct Parser<'a> {
 	pub tokenizer: Tokenizer<'a>,
+
+	// Reject expressions that continue on a new line where they could also be read as a new statement
+	pub newline_sensitive: bool,
+
+	// Syntax errors found by parse, which carries on after each one
+	pub errors: Vec<CompileError>,
+
+	// How many blocks the parser is inside
+	depth: usize
 }
 
 impl<'a> Parser<'a> {
 	pub fn new(tokenizer: Tokenizer<'a>) -> Parser<'a> {
-		return Parser { tokenizer };
+		return Parser { tokenizer, newline_sensitive: false, errors: Vec::new(), depth: 0 };
+	}
+
+	// Parses the whole program, leaving out the statements that have syntax errors
+	pub fn parse(&mut self) -> Vec<Statement> {
+		return self.statements(TokenType::EndOfFile);
+	}
+
+	// Parses statements up to the token that ends them, recording syntax errors and carrying on after each
+	fn statements(&mut self, end: TokenType) -> Vec<Statement> {
+		let mut statements = Vec::new();
+
+		loop {
+			let start = self.tokenizer.position;
+
+			let error = match self.tokenizer.peek_token() {
+				Ok(token) if token.kind == end || token.kind == TokenType::EndOfFile => return statements,
+
+				Ok(_) => match self.statement() {
+					Ok(stmt) => {
+						statements.push(stmt);
+
+						continue;
+					}
+
+					Err(error) => error
+				},
+
+				Err(error) => error
+			};
+
+			self.errors.push(error);
+
+			self.synchronize();
+
+			// Always move on, otherwise the same error would come back forever
+			if self.tokenizer.position == start {
+				self.tokenizer.get();
+			}
+		}
 	}
 
-	pub fn statement(&mut self) -> Statement {
-		let tokens = self.tokenizer.peek_many(3);
+	// Skips the rest of a broken statement, up to a ; or the end of its line
+	// A } is left for the block it closes, unless there's no block to close
+	fn synchronize(&mut self) {
+		let line = self.tokenizer.line;
+
+		loop {
+			match self.tokenizer.peek_token() {
+				Ok(token) => match token.kind {
+					TokenType::EndOfFile => return,
+
+					TokenType::RBrace if self.depth > 0 => return,
+
+					TokenType::Semicolon => {
+						let _ = self.tokenizer.next();
+
+						return;
+					}
+
+					_ if token.line > line => return,
+
+					_ => {
+						let _ = self.tokenizer.next();
+					}
+				},
+
+				// A token that can't be read is skipped a character at a time
+				Err(_) => {
+					self.tokenizer.get();
+				}
+			}
+		}
+	}
+
+	pub fn skip_separators(&mut self) -> CompileResult<()> {
+		while self.tokenizer.peek_token()?.kind == TokenType::Semicolon {
+			self.tokenizer.next()?;
+		}
+
+		return Ok(());
+	}
+
+	// Semicolons are optional, but allow several statements on one line
+	pub fn statement(&mut self) -> CompileResult<Statement> {
+		self.skip_separators()?;
+
+		// Only the doc comments right before the statement belong to it
+		self.tokenizer.doc.clear();
+
+		self.tokenizer.skip_trivia()?;
+
+		let doc = std::mem::take(&mut self.tokenizer.doc);
+
+		let mut stmt = self.simple_statement()?;
+
+		// Kept on any statement, so a doc comment that documents nothing can be warned about
+		if ! doc.is_empty() {
+			stmt.doc = Some(doc.join("\n"));
+		}
+
+		self.skip_separators()?;
+
+		return Ok(stmt);
+	}
+
+	pub fn simple_statement(&mut self) -> CompileResult<Statement> {
+		let span = self.tokenizer.peek_token()?.span();
+
+		let mut stmt = self.statement_kind()?;
+
+		stmt.span = span;
+
+		return Ok(stmt);
+	}
+
+	fn statement_kind(&mut self) -> CompileResult<Statement> {
+		let tokens = self.tokenizer.peek_many(5)?;
 
 		let token = &tokens[0];
 
+		// const NAME = expr
+		if token.kind == TokenType::Keyword && token.string == "const" {
+			self.tokenizer.next()?;
+
+			let mut stmt = Statement::new(StatementType::Const);
+
+			stmt.name = Some(self.tokenizer.expect(TokenType::Identifier)?);
+
+			self.tokenizer.expect(TokenType::Equal)?;
+
+			stmt.expression = Some(self.expression()?);
+
+			return Ok(stmt);
+		}
+
 		if tokens[2].kind == TokenType::Equal {
-			let vartype = self.tokenizer.expect(TokenType::Identifier);
+			let vartype = self.tokenizer.expect(TokenType::Identifier)?;
 
-			let varname = self.tokenizer.expect(TokenType::Identifier);
+			let varname = self.tokenizer.expect(TokenType::Identifier)?;
 
-			self.tokenizer.next(); // equals
+			self.tokenizer.next()?; // equals
 
-			let varvalue = self.expression();
+			let varvalue = self.expression()?;
 
 			let mut stmt = Statement::new(StatementType::VarDecl);
 
@@ -185,162 +409,388 @@ impl<'a> Parser<'a> {
 
 			stmt.expression = Some(varvalue);
 
-			return stmt;
+			return Ok(stmt);
 		}
 
-		if tokens[1].kind == TokenType::Equal {
-			let varname = self.tokenizer.expect(TokenType::Identifier);
+		// type[length] name
+		if tokens[1].kind == TokenType::LBracket && tokens[2].kind == TokenType::Number && tokens[3].kind == TokenType::RBracket && tokens[4].kind == TokenType::Identifier {
+			let vartype = self.tokenizer.expect(TokenType::Identifier)?;
+
+			self.tokenizer.next()?; // [
+
+			let length = self.tokenizer.expect(TokenType::Number)?;
+
+			self.tokenizer.next()?; // ]
 
-			self.tokenizer.next(); // equals
+			let varname = self.tokenizer.expect(TokenType::Identifier)?;
 
-			let varvalue = self.expression();
+			if length.number <= 0 {
+				return Err(CompileError::new(format!("Array {} needs at least one element", varname.string), length.span()));
+			}
+
+			let mut stmt = Statement::new(StatementType::VarDecl);
 
-			let mut stmt = Statement::new(StatementType::Assignment);
+			stmt.type_name = Some(vartype);
 			
 			stmt.name = Some(varname);
 
-			stmt.expression = Some(varvalue);
+			stmt.length = Some(length.number as usize);
+
+			return Ok(stmt);
+		}
 
-			return stmt;
+		if token.kind == TokenType::Identifier && matches!(tokens[1].kind, TokenType::Equal | TokenType::LBracket | TokenType::CompoundAdd | TokenType::CompoundSub | TokenType::CompoundMul | TokenType::CompoundDiv | TokenType::CompoundMod) {
+			return self.assignment();
 		}
 
 		if token.string == "if" {
-			self.tokenizer.next();
+			self.tokenizer.next()?;
 
-			let condition = self.expression();
+			let condition = self.expression()?;
 
 			let mut stmt = Statement::new(StatementType::If);
 
-			self.tokenizer.expect(TokenType::LBrace);
+			stmt.condition = Some(condition);
 
-			let mut block: Vec<Statement> = vec![];
+			stmt.block = Some(self.block()?);
 
-			while ! self.tokenizer.eof() && self.tokenizer.peek_token().kind != TokenType::RBrace {
-				block.push(self.statement());
-			}
+			let next = self.tokenizer.peek_token()?;
 
-			self.tokenizer.expect(TokenType::RBrace);
+			if next.kind == TokenType::Keyword && next.string == "else" {
+				self.tokenizer.next()?;
 
-			stmt.condition = Some(condition);
+				let next = self.tokenizer.peek_token()?;
 
-			stmt.block = Some(block);
+				// else if chains become an else block holding a single if statement
+				if next.kind == TokenType::Keyword && next.string == "if" {
+					stmt.else_block = Some(vec![self.statement()?]);
+				}
+				else {
+					stmt.else_block = Some(self.block()?);
+				}
+			}
 
-			return stmt;
+			return Ok(stmt);
 		}
 
 		if token.string == "while" {
-			self.tokenizer.next();
+			self.tokenizer.next()?;
 
-			let condition = self.expression();
+			let condition = self.expression()?;
 
 			let mut stmt = Statement::new(StatementType::While);
 
-			self.tokenizer.expect(TokenType::LBrace);
+			stmt.condition = Some(condition);
+
+			stmt.block = Some(self.block()?);
+
+			return Ok(stmt);
+		}
+
+		if token.string == "for" {
+			self.tokenizer.next()?;
+
+			let mut stmt = Statement::new(StatementType::For);
+
+			let header = self.tokenizer.peek_many(2)?;
+
+			if header[1].kind == TokenType::Keyword && header[1].string == "in" {
+				// for i in start..end is rewritten as for int i = start; i < i.end; i = i + 1
+				let var = self.tokenizer.expect(TokenType::Identifier)?;
+
+				self.tokenizer.next()?; // in
+
+				let start = self.expression()?;
+
+				self.tokenizer.expect(TokenType::DotDot)?;
+
+				let end = self.expression()?;
+
+				let int = Token::identifier_literal(String::from("int"), var.line, var.column);
+
+				// Not a valid identifier in source, so it can't collide with user variables
+				let end_var = Token::identifier_literal(format!("{}.end", var.string), var.line, var.column);
+
+				let mut init = Statement::new(StatementType::VarDecl);
+
+				init.type_name = Some(int.clone());
+
+				init.name = Some(var.clone());
+
+				init.expression = Some(start);
+
+				let mut bound = Statement::new(StatementType::VarDecl);
 
-			let mut block: Vec<Statement> = vec![];
+				bound.type_name = Some(int);
 
-			while ! self.tokenizer.eof() && self.tokenizer.peek_token().kind != TokenType::RBrace {
-				block.push(self.statement());
+				bound.name = Some(end_var.clone());
+
+				bound.expression = Some(end);
+
+				let mut step = Statement::new(StatementType::Assignment);
+
+				step.name = Some(var.clone());
+
+				step.expression = Some(Expression::new_binary(
+					Expression::new_literal(var.clone()),
+					Token::operator(TokenType::Plus, "+", var.line, var.column),
+					Expression::new_literal(Token::number_literal(1, var.line, var.column))
+				));
+
+				stmt.condition = Some(Expression::new_binary(
+					Expression::new_literal(var.clone()),
+					Token::operator(TokenType::LT, "<", var.line, var.column),
+					Expression::new_literal(end_var)
+				));
+
+				// Marks the loop as counted, which lets the compiler unroll it
+				stmt.name = Some(var);
+
+				stmt.init = Some(vec![init, bound]);
+
+				stmt.step = Some(Box::new(step));
+
+				stmt.block = Some(self.block()?);
+
+				return Ok(stmt);
 			}
 
-			self.tokenizer.expect(TokenType::RBrace);
+			stmt.init = Some(vec![self.simple_statement()?]);
 
-			stmt.condition = Some(condition);
+			self.tokenizer.expect(TokenType::Semicolon)?;
+
+			stmt.condition = Some(self.expression()?);
+
+			self.tokenizer.expect(TokenType::Semicolon)?;
+
+			stmt.step = Some(Box::new(self.simple_statement()?));
 
-			stmt.block = Some(block);
+			stmt.block = Some(self.block()?);
 
-			return stmt;
+			return Ok(stmt);
 		}
 
-		if token.string == "return" {
-			self.tokenizer.next();
+		if token.string == "fn" {
+			self.tokenizer.next()?;
+
+			let mut stmt = Statement::new(StatementType::Function);
+
+			stmt.name = Some(self.tokenizer.expect(TokenType::Identifier)?);
+
+			self.tokenizer.expect(TokenType::LParen)?;
+
+			let mut params = Vec::new();
+
+			while self.tokenizer.peek_token()?.kind != TokenType::RParen {
+				let param_type = self.tokenizer.expect(TokenType::Identifier)?;
+
+				let param_name = self.tokenizer.expect(TokenType::Identifier)?;
+
+				params.push((param_type, param_name));
+
+				if self.tokenizer.peek_token()?.kind == TokenType::Comma {
+					self.tokenizer.next()?;
+				}
+				else if self.tokenizer.peek_token()?.kind != TokenType::RParen {
+					let token = self.tokenizer.next()?;
+
+					return Err(CompileError::new(format!("Expected , or ) in parameter list but got {}", token.describe()), token.span()));
+				}
+			}
+
+			self.tokenizer.expect(TokenType::RParen)?;
 
-			let expression = self.expression();
+			if self.tokenizer.peek_token()?.kind == TokenType::Arrow {
+				self.tokenizer.next()?;
+
+				stmt.type_name = Some(self.tokenizer.expect(TokenType::Identifier)?);
+			}
+
+			stmt.params = Some(params);
+
+			stmt.block = Some(self.block()?);
+
+			return Ok(stmt);
+		}
+
+		if token.string == "return" {
+			self.tokenizer.next()?;
 
 			let mut stmt = Statement::new(StatementType::Return);
 
-			stmt.expression = Some(expression);
+			// A bare return has nothing left in the block after it
+			if self.tokenizer.peek_token()?.kind != TokenType::RBrace {
+				stmt.expression = Some(self.expression()?);
+			}
 
-			return stmt;
+			return Ok(stmt);
+		}
+
+		if token.string == "break" {
+			self.tokenizer.next()?;
+
+			return Ok(Statement::new(StatementType::Break));
+		}
+
+		if token.string == "continue" {
+			self.tokenizer.next()?;
+
+			return Ok(Statement::new(StatementType::Continue));
 		}
 		
 		let mut stmt = Statement::new(StatementType::Expression);
 
-		stmt.expression = Some(self.expression());
+		stmt.expression = Some(self.expression()?);
 
-		return stmt;
+		return Ok(stmt);
 	}
 
-	pub fn expression(&mut self) -> Expression {
-		return self.logic();
-	}
+	// name = value, name[index] = value and their compound forms
+	pub fn assignment(&mut self) -> CompileResult<Statement> {
+		let varname = self.tokenizer.expect(TokenType::Identifier)?;
 
-	pub fn logic(&mut self) -> Expression {
-		return self.comparison();
-	}
+		let mut stmt = Statement::new(StatementType::Assignment);
+
+		let mut target = Expression::new_literal(varname.clone());
 
-	pub fn comparison(&mut self) -> Expression {
-		let mut left = self.addsub();
+		if self.tokenizer.peek_token()?.kind == TokenType::LBracket {
+			self.tokenizer.next()?;
 
-		while matches!(self.tokenizer.peek_token().kind, TokenType::EqualEqual | TokenType::NotEqual | TokenType::GT | TokenType::LT | TokenType::GTEqual | TokenType::LTEqual) {
-			let operator = self.tokenizer.next();
+			let index = self.expression()?;
 
-			left = Expression::new_binary(left, operator, self.addsub());
+			self.tokenizer.expect(TokenType::RBracket)?;
+
+			target = Expression::new_index(varname.clone(), index.clone());
+
+			stmt.index = Some(index);
 		}
 
-		return left;
-	}
+		let optoken = self.tokenizer.next()?;
 
-	pub fn addsub(&mut self) -> Expression {
-		let mut left = self.mul();
-	
-		while self.tokenizer.peek_token().kind == TokenType::Plus || self.tokenizer.peek_token().kind == TokenType::Minus {
-			let operator = self.tokenizer.next();
+		// A compound assignment reads the element before writing it, so an index that calls something
+		// is stored first to only be evaluated once
+		if let Some(index) = stmt.index.take_if(|index| optoken.kind != TokenType::Equal && has_call(index)) {
+			let temp = Token::identifier_literal(format!("{}.index", varname.string), varname.line, varname.column);
 
-			let right = self.mul();
+			let mut decl = Statement::new(StatementType::VarDecl);
 
-			left = Expression::new_binary(left, operator, right);
+			decl.type_name = Some(Token::identifier_literal(String::from("int"), varname.line, varname.column));
+
+			decl.name = Some(temp.clone());
+
+			decl.expression = Some(index);
+
+			stmt.init = Some(vec![decl]);
+
+			target = Expression::new_index(varname.clone(), Expression::new_literal(temp.clone()));
+
+			stmt.index = Some(Expression::new_literal(temp));
 		}
 
-		return left;
+		// x op= y is rewritten to x = x op y
+		let compound = match optoken.kind {
+			TokenType::Equal => None,
+			TokenType::CompoundAdd => Some((TokenType::Plus, "+")),
+			TokenType::CompoundSub => Some((TokenType::Minus, "-")),
+			TokenType::CompoundMul => Some((TokenType::Times, "*")),
+			TokenType::CompoundDiv => Some((TokenType::Slash, "/")),
+			TokenType::CompoundMod => Some((TokenType::Mod, "%")),
+			_ => return Err(CompileError::new(format!("Expected an assignment but got {}", optoken.describe()), optoken.span()))
+		};
+
+		let varvalue = self.expression()?;
+
+		stmt.expression = Some(match compound {
+			Some((kind, op)) => Expression::new_binary(target, Token::operator(kind, op, optoken.line, optoken.column), varvalue),
+
+			None => varvalue
+		});
+		
+		stmt.name = Some(varname);
+
+		return Ok(stmt);
 	}
 
-	pub fn mul(&mut self) -> Expression {
-		let mut left = self.div();
+	pub fn block(&mut self) -> CompileResult<Vec<Statement>> {
+		self.tokenizer.expect(TokenType::LBrace)?;
 
-		while self.tokenizer.peek_token().kind == TokenType::Times {
-	        let operator = self.tokenizer.next();
+		self.depth += 1;
 
-			let right = self.div();
+		let block = self.statements(TokenType::RBrace);
 
-			left = Expression::new_binary(left, operator, right);
-		}
+		self.depth -= 1;
+
+		self.tokenizer.expect(TokenType::RBrace)?;
+
+		return Ok(block);
+	}
 
-		return left;
+	pub fn expression(&mut self) -> CompileResult<Expression> {
+		return self.binary(0);
+	}
+
+	// Binding strength of binary operators, higher binds tighter
+	pub fn precedence(kind: TokenType) -> Option<u8> {
+		return match kind {
+			TokenType::Or => Some(1),
+			TokenType::And => Some(2),
+			TokenType::EqualEqual | TokenType::NotEqual | TokenType::GT | TokenType::LT | TokenType::GTEqual | TokenType::LTEqual => Some(3),
+			TokenType::Plus | TokenType::Minus => Some(4),
+			TokenType::Times | TokenType::Slash | TokenType::Mod => Some(5),
+			_ => None
+		};
 	}
 
-	pub fn div(&mut self) -> Expression {
-		let mut left = self.primary();
+	// Precedence climbing, every operator is left associative
+	pub fn binary(&mut self, min: u8) -> CompileResult<Expression> {
+		let mut left = self.unary()?;
 
-		while self.tokenizer.peek_token().kind == TokenType::Slash {
-	        let operator = self.tokenizer.next();
+		while let Some(prec) = Parser::precedence(self.tokenizer.peek_token()?.kind) {
+			if prec < min {
+				break;
+			}
+
+			// Where the left side ended
+			let line = self.tokenizer.line;
 
-			let right = self.primary();
+			let operator = self.tokenizer.next()?;
+
+			// A - is the only binary operator that can also start a statement
+			if self.newline_sensitive && operator.kind == TokenType::Minus && operator.line > line {
+				return Err(Parser::ambiguous(&operator, line));
+			}
+
+			let right = self.binary(prec + 1)?;
 
 			left = Expression::new_binary(left, operator, right);
 		}
 
-		return left;
+		return Ok(left);
+	}
+
+	// A token on a new line that could either continue the expression on the line before or start a statement
+	fn ambiguous(token: &Token, line: u32) -> CompileError {
+		return CompileError::new(format!("Ambiguous statement boundary: '{}' on a new line continues the expression from line {}, add ';' to end the statement or move '{}' onto the same line", token.string, line, token.string), token.span());
+	}
+
+	pub fn unary(&mut self) -> CompileResult<Expression> {
+		if matches!(self.tokenizer.peek_token()?.kind, TokenType::Not | TokenType::Minus) {
+			let operator = self.tokenizer.next()?;
+
+			return Ok(Expression::new_unary(operator, self.unary()?));
+		}
+
+		return self.primary();
 	}
 
 	/*pub fn call(&mut self) -> Expression {
-		let mut left = self.primary();
+		let mut left = self.primary()?;
 
-		while self.tokenizer.peek_token().kind == TokenType::LParen {
-			self.tokenizer.next();
+		while self.tokenizer.peek_token()?.kind == TokenType::LParen {
+			self.tokenizer.next()?;
 
-			if self.tokenizer.peek_token().kind == TokenType::RParen {
-				self.tokenizer.next();
+			if self.tokenizer.peek_token()?.kind == TokenType::RParen {
+				self.tokenizer.next()?;
 
 				left = Expression::new_call(left, Vec::new());
 
@@ -349,56 +799,88 @@ impl<'a> Parser<'a> {
 
 			let mut args = Vec::new();
 
-			while self.tokenizer.peek_token().kind != TokenType::RParen {
-				args.push(self.expression());
+			while self.tokenizer.peek_token()?.kind != TokenType::RParen {
+				args.push(self.expression()?);
 
-				if self.tokenizer.peek_token().kind == TokenType::Comma {
-					self.tokenizer.next();
+				if self.tokenizer.peek_token()?.kind == TokenType::Comma {
+					self.tokenizer.next()?;
 				}
-				else if self.tokenizer.peek_token().kind != TokenType::RParen {
-					panic!("Expected , or )");
+				else if self.tokenizer.peek_token()?.kind != TokenType::RParen {
+					let token = self.tokenizer.next()?;
+
+					return Err(CompileError::new(format!("Expected , or ) but got {}", token.describe()), token.span()));
 				}
 			}
 
-			self.tokenizer.next();
+			self.tokenizer.next()?;
 
 			left = Expression::new_call(left, args);
 		}
 
-		return left;
+		return Ok(left);
 			
 	}*/
 
-	pub fn primary(&mut self) -> Expression {
-		let token = self.tokenizer.next();
+	pub fn primary(&mut self) -> CompileResult<Expression> {
+		let token = self.tokenizer.next()?;
+
+		if token.kind == TokenType::LParen {
+			let expr = self.expression()?;
 
-		if self.tokenizer.peek_token().kind == TokenType::LParen {
-			self.tokenizer.next();
+			self.tokenizer.expect(TokenType::RParen)?;
 
-			if self.tokenizer.peek_token().kind == TokenType::RParen {
-				self.tokenizer.next();
+			return Ok(expr);
+		}
+
+		if ! matches!(token.kind, TokenType::Identifier | TokenType::Number | TokenType::Char | TokenType::String) {
+			return Err(CompileError::new(format!("Expected an expression but got {}", token.describe()), token.span()));
+		}
+
+		let next = self.tokenizer.peek_token()?;
+
+		if next.kind == TokenType::LParen {
+			if self.newline_sensitive && next.line > token.line {
+				return Err(Parser::ambiguous(&next, token.line));
+			}
+
+			self.tokenizer.next()?;
 
-				return Expression::new_call(token, Vec::new());
+			if self.tokenizer.peek_token()?.kind == TokenType::RParen {
+				self.tokenizer.next()?;
+
+				return Ok(Expression::new_call(token, Vec::new()));
 			}
 
 			let mut args = Vec::new();
 
-			while self.tokenizer.peek_token().kind != TokenType::RParen {
-				args.push(self.expression());
+			while self.tokenizer.peek_token()?.kind != TokenType::RParen {
+				args.push(self.expression()?);
 
-				if self.tokenizer.peek_token().kind == TokenType::Comma {
-					self.tokenizer.next();
+				if self.tokenizer.peek_token()?.kind == TokenType::Comma {
+					self.tokenizer.next()?;
 				}
-				else if self.tokenizer.peek_token().kind != TokenType::RParen {
-					panic!("Expected , or )");
+				else if self.tokenizer.peek_token()?.kind != TokenType::RParen {
+					let token = self.tokenizer.next()?;
+
+					return Err(CompileError::new(format!("Expected , or ) but got {}", token.describe()), token.span()));
 				}
 			}
 
-			self.tokenizer.next();
+			self.tokenizer.next()?;
 				
-			return Expression::new_call(token, args);
+			return Ok(Expression::new_call(token, args));
+		}
+
+		if next.kind == TokenType::LBracket {
+			self.tokenizer.next()?;
+
+			let index = self.expression()?;
+
+			self.tokenizer.expect(TokenType::RBracket)?;
+
+			return Ok(Expression::new_index(token, index));
 		}
 
-		return Expression::new_literal(token);
+		return Ok(Expression::new_literal(token));
 	}
 }
\ No newline at end of file
@@ -2,6 +2,8 @@
 
 use std::vec::Vec;
 
+use super::{CompileError, CompileResult, Span};
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
 	Identifier,
@@ -27,10 +29,16 @@ pub enum TokenType {
 	CompoundMod,
 
 	Comma,
+	Semicolon,
+	DotDot,
+	Arrow,
 
 	Not,
 	NotEqual,
 
+	And,
+	Or,
+
 	Equal,
 	EqualEqual,
 	
@@ -40,10 +48,16 @@ pub enum TokenType {
 	LBrace,
 	RBrace,
 
+	LBracket,
+	RBracket,
+
 	GT,
 	LT,
 	GTEqual,
 	LTEqual,
+
+	// A character that can't start any token, left for the parser to report
+	Error,
 	
 	EndOfFile
 }
@@ -58,9 +72,20 @@ pub struct Token {
 	
 	pub number: i64,
 
+	// Where the token starts, lines and columns start at 1
 	pub line: u32,
 	
-	pub column: u32
+	pub column: u32,
+
+	// Where the next character after the token is
+	pub end_line: u32,
+
+	pub end_column: u32,
+
+	// Byte offsets of the token in the source, end is exclusive
+	pub start: usize,
+
+	pub end: usize
 }
 
 impl Token {
@@ -71,7 +96,11 @@ impl Token {
 			string: String::new(),
 			number: 0,
 			line,
-			column
+			column,
+			end_line: line,
+			end_column: column,
+			start: 0,
+			end: 0
 		};
 	}
 
@@ -115,6 +144,24 @@ impl Token {
 		return token;
 	}
 
+	pub fn span(&self) -> Span {
+		return Span::new(self.line, self.column);
+	}
+
+	// How the token reads in an error message
+	pub fn describe(&self) -> String {
+		return match self.kind {
+			TokenType::EndOfFile => "end of file".to_string(),
+			TokenType::Number => format!("number {}", self.number),
+			TokenType::Char => format!("character {:?}", self.char),
+			TokenType::String => format!("string {:?}", self.string),
+			TokenType::Identifier => format!("identifier {}", self.string),
+			TokenType::Keyword => format!("keyword {}", self.string),
+			TokenType::Error => format!("invalid character {:?}", self.char),
+			_ => format!("'{}'", self.string)
+		};
+	}
+
 	pub fn operator(kind: TokenType, string: &str, line: u32, column: u32) -> Token {
 		return {
 			let mut tok = Token::new(kind, line, column);
@@ -133,7 +180,10 @@ pub struct Tokenizer<'a> {
 
 	pub line: u32,
 
-	pub column: u32
+	pub column: u32,
+
+	// Lines of /// comments skipped since the last time the parser took them
+	pub doc: Vec<String>
 }
 
 fn is_whitespace(c: char) -> bool {
@@ -148,11 +198,11 @@ fn is_alpha(c: char) -> bool {
 	return (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_';
 }
 
-const KEYWORDS: [&str;3] = ["if", "while", "for"];
+const KEYWORDS: [&str;10] = ["if", "else", "while", "for", "in", "fn", "return", "break", "continue", "const"];
 
 impl<'a> Tokenizer<'a> {
-	pub fn new(source: &'a [u8]) -> Tokenizer {
-		return Tokenizer { position: 0, source, line: 1, column: 1 };
+	pub fn new(source: &'a [u8]) -> Tokenizer<'a> {
+		return Tokenizer { position: 0, source, line: 1, column: 1, doc: Vec::new() };
 	}
 
 	pub fn eof(&self) -> bool {
@@ -189,52 +239,93 @@ impl<'a> Tokenizer<'a> {
 		return Some(self.source[self.position] as char);
 	}
 
-	pub fn next(&mut self) -> Token {
-		if self.position >= self.source.len() {
-			return Token::new(TokenType::EndOfFile, self.line, self.column);
-		}
+	// Reads the next token and records where it is in the source
+	pub fn next(&mut self) -> CompileResult<Token> {
+		self.skip_trivia()?;
 
-		while is_whitespace(self.peek().unwrap()) {
-			self.position += 1;
-		}
+		let (start, line, column) = (self.position, self.line, self.column);
 
-		let c = self.get().unwrap();
+		let mut token = self.token(line, column)?;
 
-		if c == '/' && self.peek().unwrap() == '/' {
-			while self.peek().unwrap() != '\n' {
-				self.get();
+		token.line = line;
+
+		token.column = column;
+
+		token.end_line = self.line;
+
+		token.end_column = self.column;
+
+		token.start = start;
+
+		token.end = self.position;
+
+		return Ok(token);
+	}
+
+	// The token starting at the current position, after trivia has been skipped
+	fn token(&mut self, line: u32, column: u32) -> CompileResult<Token> {
+		let Some(c) = self.get() else {
+			return Ok(Token::new(TokenType::EndOfFile, line, column));
+		};
+
+		// Raw strings keep backslashes as they are
+		if c == 'r' && self.peek() == Some('"') {
+			self.get();
+
+			let mut string = String::new();
+
+			while self.peek() != Some('"') {
+				let Some(c) = self.get() else {
+					return Err(CompileError::new("Unexpected end of file, expecting '\"' to close string".to_string(), Span::new(line, column)));
+				};
+
+				string.push(c);
 			}
 
-			return self.next();
+			self.get();
+
+			return Ok(Token::string_literal(string, line, column));
 		}
 
 		match c {
 			'"' => {
 				let mut string = String::new();
 
-				while self.peek().unwrap() != '"' {
-					string.push(self.get().unwrap());
+				while self.peek() != Some('"') {
+					match self.get() {
+						Some('\\') => string.extend(self.escape()?),
+
+						Some(c) => string.push(c),
 
-					if self.peek() == None {
-						panic!("Unexpected end of file, expecting '\"' to close string.");
+						None => return Err(CompileError::new("Unexpected end of file, expecting '\"' to close string".to_string(), Span::new(line, column)))
 					}
 				}
 
-				self.get().unwrap();
+				self.get();
 
-				return Token::string_literal(string, self.line, self.column);
+				return Ok(Token::string_literal(string, line, column));
 			}
 
 			'\'' => {
-				let char = self.get().unwrap();
+				let chars = match self.get() {
+					Some('\\') => self.escape()?,
+
+					Some(c) => vec![c],
+
+					None => return Err(CompileError::new("Unexpected end of file in character literal".to_string(), Span::new(line, column)))
+				};
 
-				if self.peek() == None || self.peek().unwrap() != '\'' {
-					panic!("Expected ' to close character literal.");
+				if chars.len() != 1 {
+					return Err(CompileError::new("Character literal doesn't fit in a single cell".to_string(), Span::new(line, column)));
+				}
+
+				if self.peek() != Some('\'') {
+					return Err(CompileError::new("Expected ' to close character literal".to_string(), Span::new(line, column)));
 				}
 
 				self.get();
 
-				return Token::char_literal(char, self.line, self.column);
+				return Ok(Token::char_literal(chars[0], line, column));
 			}
 
 			_ => {}
@@ -243,124 +334,141 @@ impl<'a> Tokenizer<'a> {
 		match c {
 			'=' => match self.peek() {
 				Some('=') => {
-					self.position += 2;
+					self.get();
 
-					return Token::operator(TokenType::EqualEqual, "==", self.line, self.column);
+					return Ok(Token::operator(TokenType::EqualEqual, "==", line, column));
 				}
 
 					
 				_ => {
-					self.position += 1;
-
-					return Token::operator(TokenType::Equal, "=", self.line, self.column);
+					return Ok(Token::operator(TokenType::Equal, "=", line, column));
 				}
 			},
 
 			'!' => match self.peek() {
 				Some('=') => {
-					self.position += 2;
+					self.get();
 
-					return Token::operator(TokenType::NotEqual, "!=", self.line, self.column);
+					return Ok(Token::operator(TokenType::NotEqual, "!=", line, column));
 				}
 
 					
 				_ => {
-					self.position += 1;
-
-					return Token::operator(TokenType::Not, "!", self.line, self.column);
+					return Ok(Token::operator(TokenType::Not, "!", line, column));
 				}
 			},
 			
+			'&' if self.peek() == Some('&') => {
+				self.get();
+
+				return Ok(Token::operator(TokenType::And, "&&", line, column));
+			},
+
+			'|' if self.peek() == Some('|') => {
+				self.get();
+
+				return Ok(Token::operator(TokenType::Or, "||", line, column));
+			},
+			
 			'+' => match self.peek() {
 					Some('=') => {
-						self.position += 2;
+						self.get();
 						
-						return Token::operator(TokenType::CompoundAdd, "+=", self.line, self.column);
+						return Ok(Token::operator(TokenType::CompoundAdd, "+=", line, column));
 				},
 
 				_ => {
-					self.position += 1;
-					
-					return Token::operator(TokenType::Plus, "+", self.line, self.column);
+					return Ok(Token::operator(TokenType::Plus, "+", line, column));
 				}
 			},
 
 			
 			'-' => match self.peek() {
 				Some('=') => {
-					self.position += 2;
+					self.get();
+
+					return Ok(Token::operator(TokenType::CompoundSub, "-=", line, column));
+				}
 
-					return Token::operator(TokenType::CompoundSub, "-=", self.line, self.column);
+				Some('>') => {
+					self.get();
+
+					return Ok(Token::operator(TokenType::Arrow, "->", line, column));
 				}
 
 					
 				_ => {
-					self.position += 1;
-
-					return Token::operator(TokenType::Minus, "-", self.line, self.column);
+					return Ok(Token::operator(TokenType::Minus, "-", line, column));
 				}
 			},
 
 			'*' => match self.peek() {
 				Some('=') => {
-					self.position += 2;
+					self.get();
 
-					return Token::operator(TokenType::CompoundMul, "*=", self.line, self.column);
+					return Ok(Token::operator(TokenType::CompoundMul, "*=", line, column));
 				}
 
 					
 				_ => {
-					self.position += 1;
-
-					return Token::operator(TokenType::Times, "*", self.line, self.column);
+					return Ok(Token::operator(TokenType::Times, "*", line, column));
 				}
 			}
 
 			'/' => match self.peek() {
 				Some('=') => {
-					self.position += 2;
+					self.get();
 
-					return Token::operator(TokenType::CompoundDiv, "/=", self.line, self.column);
+					return Ok(Token::operator(TokenType::CompoundDiv, "/=", line, column));
 				}
 
 					
 				_ => {
-					self.position += 1;
+					return Ok(Token::operator(TokenType::Slash, "/", line, column));
+				}
+			}
+
+			'%' => match self.peek() {
+				Some('=') => {
+					self.get();
+
+					return Ok(Token::operator(TokenType::CompoundMod, "%=", line, column));
+				}
 
-					return Token::operator(TokenType::Slash, "/", self.line, self.column);
+				_ => {
+					return Ok(Token::operator(TokenType::Mod, "%", line, column));
 				}
 			}
 
 			'>' => match self.peek() {
 				Some('=') => {
-					self.position += 2;
+					self.get();
 
-					return Token::operator(TokenType::GTEqual, ">=", self.line, self.column);
+					return Ok(Token::operator(TokenType::GTEqual, ">=", line, column));
 				}
 
 				_ => {
-					self.position += 1;
-					
-					return Token::operator(TokenType::GT, ">", self.line, self.column);
+					return Ok(Token::operator(TokenType::GT, ">", line, column));
 				}
 			},
 
 			'<' => match self.peek() {
 				Some('=') => {
-					self.position += 2;
+					self.get();
 
-					return Token::operator(TokenType::LTEqual, "<=", self.line, self.column);
+					return Ok(Token::operator(TokenType::LTEqual, "<=", line, column));
 				}
 
 				_ => {
-					self.position += 1;
-
-					return Token::operator(TokenType::LT, "<", self.line, self.column);
+					return Ok(Token::operator(TokenType::LT, "<", line, column));
 				}
 			},
 
+			// c is never a newline here, so stepping back a column is enough
 			_ => {
 				self.position -= 1;
+
+				self.column -= 1;
 			}
 		}
 
@@ -368,75 +476,299 @@ impl<'a> Tokenizer<'a> {
 			'(' => {
 				self.get();
 				
-				return Token::operator(TokenType::LParen, "(", self.line, self.column);
+				return Ok(Token::operator(TokenType::LParen, "(", line, column));
 			}
 
 			')' => {
 				self.get();
 				
-				return Token::operator(TokenType::RParen, ")", self.line, self.column);
+				return Ok(Token::operator(TokenType::RParen, ")", line, column));
 			}
 
 			'{' => {
 				self.get();
 				
-				return Token::operator(TokenType::LBrace, "{", self.line, self.column);
+				return Ok(Token::operator(TokenType::LBrace, "{", line, column));
 			},
 			
 			'}' => {
 				self.get();
 				
-				return Token::operator(TokenType::RBrace, "}", self.line, self.column);
+				return Ok(Token::operator(TokenType::RBrace, "}", line, column));
+			},
+
+			'[' => {
+				self.get();
+				
+				return Ok(Token::operator(TokenType::LBracket, "[", line, column));
+			},
+
+			']' => {
+				self.get();
+				
+				return Ok(Token::operator(TokenType::RBracket, "]", line, column));
 			},
 
 			',' => {
 				self.get();
 				
-				return Token::operator(TokenType::Comma, ",", self.line, self.column);
+				return Ok(Token::operator(TokenType::Comma, ",", line, column));
+			},
+
+			';' => {
+				self.get();
+				
+				return Ok(Token::operator(TokenType::Semicolon, ";", line, column));
+			},
+
+			'.' if self.source.get(self.position + 1) == Some(&b'.') => {
+				self.get();
+
+				self.get();
+				
+				return Ok(Token::operator(TokenType::DotDot, "..", line, column));
 			},
 
 			_ => {}
 		}
 
-		if is_digit(self.peek().unwrap()) {
-			let mut number = String::new();
-			
-			while ! self.eof() && is_digit(self.peek().unwrap()) {
-				number.push(self.get().unwrap());
-			}
+		if is_digit(c) {
+			return self.number();
+		}
+
+		// Always consumed, so a stray character can't stop the tokenizer from moving on
+		if ! is_alpha(c) {
+			self.get();
+
+			let mut token = Token::new(TokenType::Error, line, column);
 
-			return Token::number_literal(number.parse().unwrap(), self.line, self.column);
+			token.char = c;
+
+			token.string = c.to_string();
+
+			return Ok(token);
 		}
 
 		let mut id = String::new();
 
-		while ! self.eof() && (is_alpha(self.peek().unwrap()) || is_digit(self.peek().unwrap())) {
-			id.push(self.get().unwrap());
+		while let Some(c) = self.peek().filter(|&c| is_alpha(c) || is_digit(c)) {
+			self.get();
+
+			id.push(c);
 		}
 
 		if KEYWORDS.contains(&id.as_str()) {
-			return Token::keyword_literal(id, self.line, self.column);
+			return Ok(Token::keyword_literal(id, line, column));
 		}
 
-		return Token::identifier_literal(id, self.line, self.column);
+		return Ok(Token::identifier_literal(id, line, column));
 	}
 
-	pub fn expect(&mut self, t: TokenType) -> Token {
-		let tok = self.next();
+	// Decimal, 0x hex, 0b binary or 0o octal, with underscores allowed between digits
+	fn number(&mut self) -> CompileResult<Token> {
+		let (line, column) = (self.line, self.column);
+
+		let prefix = match (self.peek(), self.source.get(self.position + 1).map(|&c| c as char)) {
+			(Some('0'), Some('x')) => Some(16),
+			(Some('0'), Some('b')) => Some(2),
+			(Some('0'), Some('o')) => Some(8),
+			_ => None
+		};
+
+		if prefix.is_some() {
+			self.get();
+
+			self.get();
+		}
+
+		let radix = prefix.unwrap_or(10);
+
+		let mut digits = String::new();
+
+		while self.peek().is_some_and(|c| is_alpha(c) || is_digit(c)) {
+			let c = self.get().unwrap();
+
+			if c == '_' {
+				continue;
+			}
+
+			if ! c.is_digit(radix) {
+				return Err(CompileError::new(format!("Invalid digit '{}' in base {} number", c, radix), Span::new(line, column)));
+			}
+
+			digits.push(c);
+		}
+
+		if digits.is_empty() {
+			return Err(CompileError::new("Number has no digits".to_string(), Span::new(line, column)));
+		}
+
+		let Ok(number) = i64::from_str_radix(&digits, radix) else {
+			return Err(CompileError::new("Number is too large".to_string(), Span::new(line, column)));
+		};
+
+		return Ok(Token::number_literal(number, line, column));
+	}
+
+	// Reads the rest of an escape sequence after its backslash, one char per cell
+	fn escape(&mut self) -> CompileResult<Vec<char>> {
+		let (line, column) = (self.line, self.column - 1);
+
+		let invalid = |reason: String| Err(CompileError::new(format!("Invalid escape sequence: {}", reason), Span::new(line, column)));
+
+		let c = self.get();
+
+		let simple = match c {
+			Some('n') => Some('\n'),
+			Some('t') => Some('\t'),
+			Some('r') => Some('\r'),
+			Some('0') => Some('\0'),
+			Some('\\') => Some('\\'),
+			Some('\'') => Some('\''),
+			Some('"') => Some('"'),
+			_ => None
+		};
+
+		if let Some(simple) = simple {
+			return Ok(vec![simple]);
+		}
+
+		match c {
+			Some('x') => {
+				let mut digits = String::new();
+
+				while digits.len() < 2 && self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+					digits.push(self.get().unwrap());
+				}
+
+				if digits.len() != 2 {
+					return invalid("\\x needs two hex digits".to_string());
+				}
+
+				return Ok(vec![u8::from_str_radix(&digits, 16).unwrap() as char]);
+			}
+
+			// Unicode escapes take as many cells as their UTF-8 encoding
+			Some('u') => {
+				if self.get() != Some('{') {
+					return invalid("expected { after \\u".to_string());
+				}
+
+				let mut digits = String::new();
+
+				while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+					digits.push(self.get().unwrap());
+				}
+
+				if self.get() != Some('}') {
+					return invalid("expected } to close \\u{".to_string());
+				}
+
+				let code = u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32);
+
+				if digits.is_empty() || digits.len() > 6 || code.is_none() {
+					return invalid(format!("\\u{{{}}} isn't a valid character", digits));
+				}
+
+				let mut bytes = [0u8; 4];
+
+				return Ok(code.unwrap().encode_utf8(&mut bytes).bytes().map(|byte| byte as char).collect());
+			}
+
+			Some(c) => invalid(format!("\\{}", c)),
+
+			None => Err(CompileError::new("Unexpected end of file in escape sequence".to_string(), Span::new(line, column)))
+		}
+	}
+
+	// Skips whitespace and comments, keeping the text of doc comments
+	// Skipped through get so newlines are counted
+	pub fn skip_trivia(&mut self) -> CompileResult<()> {
+		loop {
+			while self.peek().is_some_and(is_whitespace) {
+				self.get();
+			}
+
+			let next = self.source.get(self.position + 1).map(|&c| c as char);
+
+			match (self.peek(), next) {
+				(Some('/'), Some('/')) => {
+					let mut text = String::new();
+
+					while self.peek().is_some_and(|c| c != '\n') {
+						text.push(self.get().unwrap());
+					}
+
+					// Exactly three slashes, //// is still a regular comment
+					if text.starts_with("///") && ! text.starts_with("////") {
+						self.doc.push(text[3..].trim().to_string());
+					}
+				}
+
+				(Some('/'), Some('*')) => self.block_comment()?,
+
+				_ => return Ok(())
+			}
+		}
+	}
+
+	// Block comments nest, so commenting out code that has one still works
+	fn block_comment(&mut self) -> CompileResult<()> {
+		let (line, column) = (self.line, self.column);
+
+		let mut depth = 0;
+
+		loop {
+			let next = self.source.get(self.position + 1).map(|&c| c as char);
+
+			match (self.peek(), next) {
+				(Some('/'), Some('*')) => {
+					self.get();
+
+					self.get();
+
+					depth += 1;
+				}
+
+				(Some('*'), Some('/')) => {
+					self.get();
+
+					self.get();
+
+					depth -= 1;
+
+					if depth == 0 {
+						return Ok(());
+					}
+				}
+
+				(Some(_), _) => {
+					self.get();
+				}
+
+				(None, _) => return Err(CompileError::new("Unterminated block comment".to_string(), Span::new(line, column)))
+			}
+		}
+	}
+
+	pub fn expect(&mut self, t: TokenType) -> CompileResult<Token> {
+		let tok = self.next()?;
 
 		if tok.kind != t {
-			panic!("Expected {:?} but got {:?}", t, tok);
+			return Err(CompileError::new(format!("Expected {:?} but got {}", t, tok.describe()), tok.span()));
 		}
 
-		return tok;
+		return Ok(tok);
 	}
 
-	pub fn peek_token(&mut self) -> Token {
+	pub fn peek_token(&mut self) -> CompileResult<Token> {
 		let pos = self.position;
 		
 		let line = self.line;
 
 		let column = self.column;
+
+		let docs = self.doc.len();
 		
 		let token = self.next();
 
@@ -446,20 +778,49 @@ impl<'a> Tokenizer<'a> {
 
 		self.column = column;
 
+		self.doc.truncate(docs);
+
 		return token;
 	}
 
-	pub fn peek_many(&mut self, n: usize) -> Vec<Token> {
+	pub fn peek_many(&mut self, n: usize) -> CompileResult<Vec<Token>> {
 		let pos = self.position;
 		
 		let line = self.line;
 
 		let column = self.column;
+
+		let docs = self.doc.len();
 		
 		let mut tokens = Vec::new();
 
-		for _ in 0..n {
-			tokens.push(self.next());
+		// Restored even when a token fails, the error comes back when it's read for real
+		for i in 0..n {
+			match self.next() {
+				Ok(token) => tokens.push(token),
+
+				// Only the first token has to be readable, the ones after a bad one are error tokens
+				// so the lookahead doesn't match them and the error is reported where they're read
+				Err(error) if i > 0 => {
+					while tokens.len() < n {
+						tokens.push(Token::new(TokenType::Error, error.span.line, error.span.column));
+					}
+
+					break;
+				}
+
+				Err(error) => {
+					self.position = pos;
+
+					self.line = line;
+
+					self.column = column;
+
+					self.doc.truncate(docs);
+
+					return Err(error);
+				}
+			}
 		}
 
 		self.position = pos;
@@ -468,6 +829,8 @@ impl<'a> Tokenizer<'a> {
 
 		self.column = column;
 
-		return tokens;
+		self.doc.truncate(docs);
+
+		return Ok(tokens);
 	}
 }
\ No newline at end of file
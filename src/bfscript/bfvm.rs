@@ -4,7 +4,10 @@ use std::io::prelude::*;
 use std::vec::Vec;
 use std::marker::PhantomData;
 
-use super::{CellSize, Debug};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::{CellSize, CompileError, Debug, Span};
 
 pub struct BFBuilder {
 	pub data: String,
@@ -24,7 +27,7 @@ impl BFBuilder {
 	}
 
 	pub fn nl(&mut self) -> &mut BFBuilder {
-		self.data += &"\n";
+		self.data += "\n";
 
 		return self;
 	}
@@ -145,7 +148,7 @@ impl Instruction for Set {
 
 		builder.instruction(owner, &mut Clear::new(self.cell));
 
-		builder.string(&"+".repeat(self.value as usize));
+		builder.string(&"+".repeat(self.value as usize % 256));
 
 		return builder.data;
 	}
@@ -197,7 +200,7 @@ impl Instruction for Add {
 	fn compile(&mut self, owner: &mut Generator) -> String {
 		let mut builder = BFBuilder::new(owner.indent);
 
-		// builder.string(&"Add:\n");
+		// builder.string("Add:\n");
 
 		// println!("Add: {} {}", self.a, self.b);
 
@@ -256,9 +259,9 @@ impl Instruction for Sub {
 			return;
 		}
 
-		let left: CellSize = owner.memory.get(self.a).into();
+		let left: CellSize = owner.memory.get(self.a);
 
-		let right: CellSize = owner.memory.get(self.b).into();
+		let right: CellSize = owner.memory.get(self.b);
 
 		owner.memory.set(self.a, CellSize::overflowing_sub(left, right).0);
 	}
@@ -317,6 +320,43 @@ impl Instruction for Clear {
 	}
 }
 
+// Adds a constant to a cell, the pool wraps it like the interpreter does
+pub struct Offset {
+	pub cell: usize,
+
+	pub value: CellSize
+}
+
+impl Offset {
+	pub fn new(cell: usize, value: CellSize) -> Offset {
+		return Offset { cell, value };
+	}
+}
+
+impl Instruction for Offset {
+	fn simulate(&mut self, owner: &mut Generator) {
+		owner.cell = self.cell;
+
+		if owner.memory.is_dirty(self.cell) {
+			return;
+		}
+
+		owner.memory.set(self.cell, owner.memory.get(self.cell) + self.value);
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		builder.instruction(owner, &mut Goto::new(self.cell)).simulate(owner);
+
+		builder.string(&"+".repeat(self.value as usize % 256));
+
+		builder.nl();
+
+		return builder.data;
+	}
+}
+
 pub struct Move {
 	pub a: usize,
 
@@ -455,6 +495,94 @@ x[[-]temp0-x]temp0[-x+temp0] */
 	}
 }
 
+// Boolean && and || built from BoolNegate, the result ends up in a as 0 or 1
+pub struct Logic<'a> {
+	pub or: bool,
+
+	pub a: usize,
+
+	pub b: usize,
+
+	pub tmp: usize,
+
+	// Instructions that evaluate b, skipped when a alone decides the result at compile time
+	pub right: BFBlock<'a>,
+
+	state: Option<Generator>
+}
+
+impl<'a> Logic<'a> {
+	pub fn new(or: bool, a: usize, tmp: usize) -> Logic<'a> {
+		return Logic { or, a, b: 0, tmp, right: BFBlock::new(), state: None };
+	}
+
+	fn add(builder: &mut BFBuilder, owner: &mut Generator, instr: &mut impl Instruction) {
+		builder.instruction(owner, instr).simulate(owner);
+	}
+}
+
+impl<'a> Instruction for Logic<'a> {
+	fn simulate(&mut self, owner: &mut Generator) {
+		if let Some(state) = self.state.take() {
+			*owner = state;
+		}
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		let mut local = owner.clone();
+
+		if ! local.memory.is_dirty(self.a) {
+			let left = local.memory.get(self.a) != 0;
+
+			// Short circuit, false && x and true || x never need x
+			if left == self.or {
+				Logic::add(&mut builder, &mut local, &mut Set::new(self.a, left as CellSize));
+
+				owner.cell = local.cell;
+
+				self.state = Some(local);
+
+				return builder.data;
+			}
+		}
+
+		for instr in &mut self.right.instructions {
+			builder.string(&instr.compile(&mut local));
+
+			instr.simulate(&mut local);
+		}
+
+		if self.or {
+			// !!(!!a + !!b)
+			for cell in [self.a, self.a, self.b, self.b] {
+				Logic::add(&mut builder, &mut local, &mut BoolNegate::new(cell, self.tmp));
+			}
+
+			Logic::add(&mut builder, &mut local, &mut Add::new(self.a, self.b));
+
+			Logic::add(&mut builder, &mut local, &mut BoolNegate::new(self.a, self.tmp));
+		}
+		else {
+			// !(!a + !b)
+			Logic::add(&mut builder, &mut local, &mut BoolNegate::new(self.a, self.tmp));
+
+			Logic::add(&mut builder, &mut local, &mut BoolNegate::new(self.b, self.tmp));
+
+			Logic::add(&mut builder, &mut local, &mut Add::new(self.a, self.b));
+		}
+
+		Logic::add(&mut builder, &mut local, &mut BoolNegate::new(self.a, self.tmp));
+
+		owner.cell = local.cell;
+
+		self.state = Some(local);
+
+		return builder.data;
+	}
+}
+
 pub struct Copy {
 	pub a: usize,
 
@@ -543,6 +671,14 @@ impl Div {
 		return self.block.unwrap() + 5;
 	}
 
+	pub fn remainder(&mut self, owner: &mut Generator) -> usize {
+		if self.block == None {
+			self.block = Some(owner.memory.alloc(6));
+		}
+		
+		return self.block.unwrap() + 1;
+	}
+
 	pub fn clean(&self, owner: &mut Generator) {
 		owner.memory.free(self.block.unwrap());
 
@@ -562,7 +698,29 @@ impl Instruction for Div {
 			self.block = Some(owner.memory.alloc(6));
 		}
 		
-		owner.cell = self.block.unwrap();
+		let block = self.block.unwrap();
+
+		owner.cell = block;
+
+		for i in 0..4 {
+			owner.memory.set(block + i, 0);
+		}
+
+		owner.memory.set(block + 5, 0);
+
+		if owner.memory.is_dirty(self.a) || owner.memory.is_dirty(self.b) {
+			owner.memory.set(self.a, 0);
+
+			owner.memory.set(self.b, 0);
+
+			owner.memory.dirty(block + 1);
+
+			owner.memory.dirty(block + 4);
+
+			owner.memory.dirty(block + 5);
+
+			return;
+		}
 
 		let a = owner.memory.get(self.a);
 
@@ -572,21 +730,22 @@ impl Instruction for Div {
 
 		owner.memory.set(self.b, 0);
 
-		owner.memory.set(self.block.unwrap(), 0);
-		
-		if a != 0 && b != 0 {
-			owner.memory.set(self.block.unwrap() + 1, a % b);
-		}
-
-		owner.memory.set(self.block.unwrap() + 2, 0);
+		// Dividing by zero never resets the counter, so the remainder is just A
+		let q = a.checked_div(b).unwrap_or(0);
 
-		owner.memory.set(self.block.unwrap() + 3, 0);
+		let r = a.checked_rem(b).unwrap_or(a);
 
-		owner.memory.set(self.block.unwrap() + 4, b);
+		owner.memory.set(block + 1, r);
 
-		if a != 0 && b != 0 {
-			owner.memory.set(self.block.unwrap() + 5, a / b);
+		// B' counts down from B and is refilled whenever it reaches zero
+		if r == 0 {
+			owner.memory.set(block + 4, b);
 		}
+		else {
+			owner.memory.set(block + 4, b.wrapping_sub(r) & 0xFF);
+		}
+
+		owner.memory.set(block + 5, q);
 	}
 
 	fn compile(&mut self, owner: &mut Generator) -> String {
@@ -596,7 +755,7 @@ impl Instruction for Div {
 		
 		let mut builder = BFBuilder::new(owner.indent);
 
-		builder.string(&"\nDIV:\n");
+		builder.string("\nDIV:\n");
 
 		builder.instruction(owner, &mut Move::new(self.block.unwrap(), self.a));
 
@@ -621,7 +780,7 @@ impl Instruction for Div {
 
 		builder.string(&lp.end().data);*/
 
-		builder.string(&"[->+>>+>-[<-]<[<<[->>>+<<<]>>>>+<<-<]<<]");
+		builder.string("[->+>>+>-[<-]<[<<[->>>+<<<]>>>>+<<-<]<<]");
 
 		builder.nl();
 
@@ -670,12 +829,19 @@ impl Instruction for Mul {
 
 		owner.memory.set(loc + 2, 0);
 
-		if ! owner.memory.is_dirty(self.b) {
-			owner.memory.set(loc + 1, owner.memory.get(self.b));
+		let (a, b) = (owner.memory.is_dirty(self.a), owner.memory.is_dirty(self.b));
+
+		// b is copied back into its cell, and the product is only known when both sides are
+		match b {
+			true => owner.memory.dirty(loc + 1),
+
+			false => owner.memory.set(loc + 1, owner.memory.get(self.b))
 		}
 
-		if ! owner.memory.is_dirty(self.a) && ! owner.memory.is_dirty(self.b) {
-			owner.memory.set(loc + 3, owner.memory.get(self.a).overflowing_mul(owner.memory.get(self.b)).0);
+		match a || b {
+			true => owner.memory.dirty(loc + 3),
+
+			false => owner.memory.set(loc + 3, owner.memory.get(self.a).overflowing_mul(owner.memory.get(self.b)).0)
 		}
 
 		owner.memory.set(self.a, 0);
@@ -693,7 +859,7 @@ impl Instruction for Mul {
 		builder.instruction(owner, &mut Goto::new(self.block.unwrap())).simulate(owner);
 
 		// https://www.codingame.com/playgrounds/50426/getting-started-with-brainfuck/multiplication
-		builder.string(&"[>[->+>+<<]>[-<+>]<<-]");
+		builder.string("[>[->+>+<<]>[-<+>]<<-]");
 
 		builder.nl();
 
@@ -857,7 +1023,7 @@ impl Instruction for Distance {
 		// builder.instruction(owner, &mut Goto::new(loc + 3)).simulate(owner);
 		builder.instruction(owner, &mut Goto::new(loc + 3)).simulate(owner);
 
-		builder.string(&"[->>[-[<]]<]<<<[>]");
+		builder.string("[->>[-[<]]<]<<<[>]");
 
 		builder.nl();
 
@@ -959,34 +1125,167 @@ impl<'a> Instruction for If<'a> {
 	fn compile(&mut self, owner: &mut Generator) -> String {
 		let mut builder = BFBuilder::new(owner.indent);
 
-		let cond = match owner.memory.is_dirty(self.condition) { true => 1, false => owner.memory.get(self.condition) };
+		let dirty = owner.memory.is_dirty(self.condition);
+
+		let cond = match dirty { true => 1, false => owner.memory.get(self.condition) };
 
 		builder.instruction(owner, &mut Goto::new(self.condition)).simulate(owner);
 
+		// The block is compiled against a copy of the state, which is kept if it runs and merged if that's only known at runtime
+		let mut body = owner.clone();
+
+		body.unreachable |= ! dirty && cond == 0;
+
+		let gen = &mut body;
+
 		let mut lp = builder.bfloop(true);
 
 			for instr in &mut self.block.instructions {
-				lp.string(&instr.compile(owner));
+				lp.string(&instr.compile(gen));
 
-				if cond != 0 {
-					instr.simulate(owner);
-				}
+				instr.simulate(gen);
 			}
 	
-			lp.instruction(owner, &mut Goto::new(self.condition)).simulate(owner);
+			lp.instruction(gen, &mut Goto::new(self.condition)).simulate(gen);
 	
-			lp.instruction(owner, &mut Clear::new(self.condition));
+			lp.instruction(gen, &mut Clear::new(self.condition));
 
 		builder.string(&lp.end().data);
 
 		builder.nl();
 
+		match (dirty, cond != 0) {
+			(true, _) => owner.memory = owner.memory.merge(&body.memory),
+
+			(false, true) => owner.memory = body.memory,
+
+			(false, false) => {}
+		}
+
+		owner.cell = self.condition;
+
 		Debug::log(&format!("If compiled: {}", builder.data));
 
 		return builder.data;
 	}
 }
 
+pub struct IfElse<'a> {
+	pub condition: usize,
+
+	// Starts at 1 and is cleared by the if block, so the else block only runs when the condition was 0
+	pub flag: usize,
+
+	pub block: BFBlock<'a>,
+
+	pub else_block: BFBlock<'a>,
+
+	// Memory state after either branch, computed while compiling and applied by simulate
+	state: Option<MemoryPool>
+}
+
+impl<'a> IfElse<'a> {
+	pub fn new(condition: usize, flag: usize) -> IfElse<'a> {
+		return IfElse {
+			condition,
+
+			flag,
+			
+			block: BFBlock::new(),
+
+			else_block: BFBlock::new(),
+
+			state: None
+		};
+	}
+}
+
+/*
+	flag[-]+
+	cond[ if_block flag- cond[-] ]
+	flag[ else_block flag- ]
+*/
+impl<'a> Instruction for IfElse<'a> {
+	fn simulate(&mut self, owner: &mut Generator) {
+		if let Some(state) = self.state.take() {
+			owner.memory = state;
+		}
+
+		owner.memory.set(self.condition, 0);
+
+		owner.memory.set(self.flag, 0);
+
+		owner.cell = self.flag;
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		let dirty = owner.memory.is_dirty(self.condition);
+
+		let cond = match dirty { true => 1, false => owner.memory.get(self.condition) };
+
+		builder.instruction(owner, &mut Set::new(self.flag, 1)).simulate(owner);
+
+		builder.instruction(owner, &mut Goto::new(self.condition)).simulate(owner);
+
+		// Each branch is compiled against its own copy of the state, so a branch that never runs can't leak into the other
+		let mut then_gen = owner.clone();
+
+		then_gen.unreachable |= ! dirty && cond == 0;
+
+		let mut lp = builder.bfloop(true);
+
+			for instr in &mut self.block.instructions {
+				lp.string(&instr.compile(&mut then_gen));
+
+				instr.simulate(&mut then_gen);
+			}
+
+			lp.instruction(&mut then_gen, &mut Clear::new(self.flag)).simulate(&mut then_gen);
+
+			lp.instruction(&mut then_gen, &mut Clear::new(self.condition)).simulate(&mut then_gen);
+
+		builder.string(&lp.end().data);
+
+		builder.nl();
+
+		builder.instruction(owner, &mut Goto::new(self.flag)).simulate(owner);
+
+		let mut else_gen = owner.clone();
+
+		else_gen.unreachable |= ! dirty && cond != 0;
+
+		else_gen.memory.set(self.condition, 0);
+
+		let mut lp = builder.bfloop(true);
+
+			for instr in &mut self.else_block.instructions {
+				lp.string(&instr.compile(&mut else_gen));
+
+				instr.simulate(&mut else_gen);
+			}
+
+			lp.instruction(&mut else_gen, &mut Clear::new(self.flag)).simulate(&mut else_gen);
+
+		builder.string(&lp.end().data);
+
+		builder.nl();
+
+		self.state = Some(match (dirty, cond != 0) {
+			(true, _) => then_gen.memory.merge(&else_gen.memory),
+
+			(false, true) => then_gen.memory,
+
+			(false, false) => else_gen.memory
+		});
+
+		Debug::log(&format!("IfElse compiled: {}", builder.data));
+
+		return builder.data;
+	}
+}
+
 pub struct While<'a> {
 	pub condition: usize,
 
@@ -1054,6 +1353,10 @@ impl<'a> Instruction for While<'a> {
 
 		builder.instruction(owner, &mut Goto::new(self.condition)).simulate(owner);
 
+		let unreachable = owner.unreachable;
+
+		owner.unreachable |= cond == 0;
+
 		let mut lp = builder.bfloop(true);
 
 			for instr in &mut self.block.instructions {
@@ -1066,6 +1369,8 @@ impl<'a> Instruction for While<'a> {
 	
 			lp.instruction(owner, &mut Goto::new(self.condition)).simulate(owner);
 
+		owner.unreachable = unreachable;
+
 		builder.string(&lp.end().data);
 
 		builder.nl();
@@ -1074,76 +1379,98 @@ impl<'a> Instruction for While<'a> {
 	}
 }
 
-pub struct Input {
-	pub cell: usize
-}
+pub struct Unroll<'a> {
+	pub counter: usize,
 
-impl Input {
-	pub fn new(cell: usize) -> Input {
-		return Input { cell };
-	}
+	pub end: usize,
+
+	pub limit: usize,
+
+	// Loop body including the step, repeated while the counter is known to be below the end
+	pub block: BFBlock<'a>,
+
+	// Runtime loop used for any iterations that can't be unrolled
+	pub fallback: BFBlock<'a>,
+
+	state: Option<Generator>
 }
 
-impl Instruction for Input {
-	fn simulate(&mut self, owner: &mut Generator) {
-		owner.memory.dirty(self.cell);
-	}
+impl<'a> Unroll<'a> {
+	pub fn new(counter: usize, end: usize, limit: usize) -> Unroll<'a> {
+		return Unroll {
+			counter,
 
-	fn compile(&mut self, owner: &mut Generator) -> String {
-		let mut builder = BFBuilder::new(owner.indent);
+			end,
 
-		builder.instruction(owner, &mut Goto::new(self.cell)).simulate(owner);
+			limit,
 
-		builder.string(&",");
+			block: BFBlock::new(),
 
-		builder.nl();
+			fallback: BFBlock::new(),
 
-		return builder.data;
+			state: None
+		};
 	}
-}
-
-pub struct Output {
-	pub cell: usize
-}
 
-impl Output {
-	pub fn new(cell: usize) -> Output {
-		return Output { cell };
+	fn known(&self, owner: &Generator) -> bool {
+		return ! owner.memory.is_dirty(self.counter) && ! owner.memory.is_dirty(self.end);
 	}
 }
 
-impl Instruction for Output {
+impl<'a> Instruction for Unroll<'a> {
 	fn simulate(&mut self, owner: &mut Generator) {
-		owner.cell = self.cell;
+		if let Some(state) = self.state.take() {
+			*owner = state;
+		}
 	}
 
 	fn compile(&mut self, owner: &mut Generator) -> String {
 		let mut builder = BFBuilder::new(owner.indent);
 
-		builder.instruction(owner, &mut Goto::new(self.cell)).simulate(owner);
+		let mut local = owner.clone();
 
-		builder.string(&".");
+		let mut iterations = 0;
 
-		builder.nl();
+		while iterations < self.limit && self.known(&local) && local.memory.get(self.counter) < local.memory.get(self.end) {
+			for instr in &mut self.block.instructions {
+				builder.string(&instr.compile(&mut local));
+
+				instr.simulate(&mut local);
+			}
+
+			iterations += 1;
+		}
+
+		Debug::log(&format!("Unrolled {} iterations", iterations));
+
+		if ! self.known(&local) || local.memory.get(self.counter) < local.memory.get(self.end) {
+			for instr in &mut self.fallback.instructions {
+				builder.string(&instr.compile(&mut local));
+
+				instr.simulate(&mut local);
+			}
+		}
+
+		owner.cell = local.cell;
+
+		self.state = Some(local);
 
 		return builder.data;
 	}
 }
 
-pub struct Command {
+pub struct Input {
 	pub cell: usize
 }
 
-impl Command {
-	pub fn new(cell: usize) -> Command {
-		return Command { cell };
+impl Input {
+	pub fn new(cell: usize) -> Input {
+		return Input { cell };
 	}
 }
 
-impl Instruction for Command {
+impl Instruction for Input {
 	fn simulate(&mut self, owner: &mut Generator) {
-		owner.cell = self.cell;
-
 		owner.memory.dirty(self.cell);
 	}
 
@@ -1152,7 +1479,7 @@ impl Instruction for Command {
 
 		builder.instruction(owner, &mut Goto::new(self.cell)).simulate(owner);
 
-		builder.string(&"@");
+		builder.string(",");
 
 		builder.nl();
 
@@ -1160,6 +1487,1133 @@ impl Instruction for Command {
 	}
 }
 
+/*
+	Reads a decimal number, stopping at the first character that isn't a digit, which is consumed as well
+	End of input reads as zero, so it stops the number too
+	Block layout:
+	C F D 10 [ COMPARE ] LT EQ [ MUL ] 10 T
+	C holds the character minus '0' and F keeps reading while it was a digit
+*/
+pub struct ReadInt {
+	pub cell: usize,
+
+	pub block: usize
+}
+
+impl ReadInt {
+	pub const SIZE: usize = 14;
+
+	pub fn new(cell: usize, block: usize) -> ReadInt {
+		return ReadInt { cell, block };
+	}
+}
+
+impl Instruction for ReadInt {
+	fn simulate(&mut self, owner: &mut Generator) {
+		owner.cell = self.block + 1;
+
+		owner.memory.dirty(self.cell);
+
+		for i in 0..ReadInt::SIZE {
+			owner.memory.dirty(self.block + i);
+		}
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		let block = self.block;
+
+		let (input, flag, digit, ten, tmp) = (block, block + 1, block + 2, block + 3, block + 13);
+
+		let (lt, eq, mul) = (block + 6, block + 7, block + 8);
+
+		builder.instruction(owner, &mut Set::new(self.cell, 0)).simulate(owner);
+
+		builder.instruction(owner, &mut Set::new(flag, 1)).simulate(owner);
+
+		// Every iteration sees different values
+		owner.memory.dirty(self.cell);
+
+		for i in 0..ReadInt::SIZE {
+			owner.memory.dirty(block + i);
+		}
+
+		builder.instruction(owner, &mut Goto::new(flag)).simulate(owner);
+
+		let mut lp = builder.bfloop(true);
+
+			lp.instruction(owner, &mut Input::new(input)).simulate(owner);
+
+			// Anything below '0' wraps around, so one comparison checks both ends
+			lp.instruction(owner, &mut Offset::new(input, 256 - b'0' as CellSize)).simulate(owner);
+
+			lp.instruction(owner, &mut Copy::new(digit, tmp, input)).simulate(owner);
+
+			lp.instruction(owner, &mut Set::new(ten, 10)).simulate(owner);
+
+			lp.instruction(owner, &mut WideCompare::new(digit, ten, 1, block + 4, lt, eq)).simulate(owner);
+
+			lp.instruction(owner, &mut Copy::new(flag, tmp, lt)).simulate(owner);
+
+			let mut accumulate = If::new(lt);
+
+			accumulate.block.add(Set::new(mul + 4, 10));
+
+			accumulate.block.add(Set::new(mul + 3, 0));
+
+			accumulate.block.add(Mul { a: self.cell, b: mul + 4, block: Some(mul) });
+
+			accumulate.block.add(Move::new(self.cell, mul + 3));
+
+			accumulate.block.add(Add::new(self.cell, input));
+
+			lp.instruction(owner, &mut accumulate).simulate(owner);
+
+			lp.instruction(owner, &mut Clear::new(input)).simulate(owner);
+
+			lp.instruction(owner, &mut Goto::new(flag)).simulate(owner);
+
+		builder.string(&lp.end().data);
+
+		builder.nl();
+
+		return builder.data;
+	}
+}
+
+// Adds one to a number stored lowest cell first, carrying into the next cell whenever one wraps to zero
+fn increment(owner: &mut Generator, cells: &[usize], flag: usize, tmp: usize) -> String {
+	let mut builder = BFBuilder::new(owner.indent);
+
+	builder.instruction(owner, &mut Goto::new(cells[0])).simulate(owner);
+
+	builder.string("+\n");
+
+	if cells.len() > 1 {
+		builder.instruction(owner, &mut Copy::new(flag, tmp, cells[0]));
+
+		builder.instruction(owner, &mut BoolNegate::new(flag, tmp));
+
+		builder.instruction(owner, &mut Goto::new(flag)).simulate(owner);
+
+		// The flag is cleared first, so carries further up can reuse it
+		let mut lp = builder.bfloop(false);
+
+		lp.string("-");
+		lp.string(&increment(owner, &cells[1..], flag, tmp));
+		lp.instruction(owner, &mut Goto::new(flag)).simulate(owner);
+
+		builder.string(&lp.end().data);
+
+		builder.nl();
+	}
+
+	return builder.data;
+}
+
+// Subtracts one from a number stored lowest cell first, borrowing from the next cell when one is already zero
+// Borrowing past the highest cell increments the borrow cell instead
+fn decrement(owner: &mut Generator, cells: &[usize], flag: usize, tmp: usize, borrow: Option<usize>) -> String {
+	let mut builder = BFBuilder::new(owner.indent);
+
+	if cells.len() > 1 || borrow.is_some() {
+		builder.instruction(owner, &mut Copy::new(flag, tmp, cells[0]));
+
+		builder.instruction(owner, &mut BoolNegate::new(flag, tmp));
+
+		builder.instruction(owner, &mut Goto::new(flag)).simulate(owner);
+
+		let mut lp = builder.bfloop(false);
+
+		lp.string("-");
+
+		if cells.len() > 1 {
+			lp.string(&decrement(owner, &cells[1..], flag, tmp, borrow));
+		}
+		else {
+			lp.instruction(owner, &mut Goto::new(borrow.unwrap())).simulate(owner);
+
+			lp.string("+");
+		}
+
+		lp.instruction(owner, &mut Goto::new(flag)).simulate(owner);
+
+		builder.string(&lp.end().data);
+
+		builder.nl();
+	}
+
+	builder.instruction(owner, &mut Goto::new(cells[0])).simulate(owner);
+
+	builder.string("-\n");
+
+	return builder.data;
+}
+
+// Integers wider than a cell, stored lowest cell first
+// Every instruction here consumes b and uses two cells starting at tmp as scratch space
+
+#[derive(Debug)]
+pub struct WideAdd {
+	pub a: usize,
+
+	pub b: usize,
+
+	pub size: usize,
+
+	pub tmp: usize
+}
+
+impl WideAdd {
+	pub fn new(a: usize, b: usize, size: usize, tmp: usize) -> WideAdd {
+		return WideAdd { a, b, size, tmp };
+	}
+}
+
+impl Instruction for WideAdd {
+	fn simulate(&mut self, owner: &mut Generator) {
+		owner.cell = self.b + self.size - 1;
+
+		if owner.memory.any_dirty(self.a, self.size) || owner.memory.any_dirty(self.b, self.size) {
+			for i in 0..self.size {
+				owner.memory.dirty(self.a + i);
+			}
+		}
+		else {
+			let value = owner.memory.get_int(self.a, self.size).wrapping_add(owner.memory.get_int(self.b, self.size));
+
+			owner.memory.set_int(self.a, self.size, value);
+		}
+
+		owner.memory.set_int(self.b, self.size, 0);
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		let cells: Vec<usize> = (self.a..self.a + self.size).collect();
+
+		for i in 0..self.size {
+			builder.instruction(owner, &mut Goto::new(self.b + i)).simulate(owner);
+
+			let mut lp = builder.bfloop(false);
+
+			lp.string("-");
+			lp.string(&increment(owner, &cells[i..], self.tmp, self.tmp + 1));
+			lp.instruction(owner, &mut Goto::new(self.b + i)).simulate(owner);
+
+			builder.string(&lp.end().data);
+
+			builder.nl();
+		}
+
+		return builder.data;
+	}
+}
+
+#[derive(Debug)]
+pub struct WideSub {
+	pub a: usize,
+
+	pub b: usize,
+
+	pub size: usize,
+
+	pub tmp: usize,
+
+	// Incremented when b is larger than a
+	pub borrow: Option<usize>
+}
+
+impl WideSub {
+	pub fn new(a: usize, b: usize, size: usize, tmp: usize) -> WideSub {
+		return WideSub { a, b, size, tmp, borrow: None };
+	}
+}
+
+impl Instruction for WideSub {
+	fn simulate(&mut self, owner: &mut Generator) {
+		owner.cell = self.b + self.size - 1;
+
+		if owner.memory.any_dirty(self.a, self.size) || owner.memory.any_dirty(self.b, self.size) {
+			for i in 0..self.size {
+				owner.memory.dirty(self.a + i);
+			}
+
+			if let Some(borrow) = self.borrow {
+				owner.memory.dirty(borrow);
+			}
+		}
+		else {
+			let a = owner.memory.get_int(self.a, self.size);
+
+			let b = owner.memory.get_int(self.b, self.size);
+
+			owner.memory.set_int(self.a, self.size, a.wrapping_sub(b));
+
+			if let Some(borrow) = self.borrow {
+				owner.memory.set(borrow, owner.memory.get(borrow) + (a < b) as CellSize);
+			}
+		}
+
+		owner.memory.set_int(self.b, self.size, 0);
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		let cells: Vec<usize> = (self.a..self.a + self.size).collect();
+
+		for i in 0..self.size {
+			builder.instruction(owner, &mut Goto::new(self.b + i)).simulate(owner);
+
+			let mut lp = builder.bfloop(false);
+
+			lp.string("-");
+			lp.string(&decrement(owner, &cells[i..], self.tmp, self.tmp + 1, self.borrow));
+			lp.instruction(owner, &mut Goto::new(self.b + i)).simulate(owner);
+
+			builder.string(&lp.end().data);
+
+			builder.nl();
+		}
+
+		return builder.data;
+	}
+}
+
+// Compares by subtracting b from a, a borrow means a was smaller and an empty difference means they were equal
+#[derive(Debug)]
+pub struct WideCompare {
+	pub a: usize,
+
+	pub b: usize,
+
+	pub size: usize,
+
+	pub tmp: usize,
+
+	pub lt: usize,
+
+	pub eq: usize
+}
+
+impl WideCompare {
+	pub fn new(a: usize, b: usize, size: usize, tmp: usize, lt: usize, eq: usize) -> WideCompare {
+		return WideCompare { a, b, size, tmp, lt, eq };
+	}
+}
+
+impl Instruction for WideCompare {
+	fn simulate(&mut self, owner: &mut Generator) {
+		owner.cell = self.a + self.size - 1;
+
+		if owner.memory.any_dirty(self.a, self.size) || owner.memory.any_dirty(self.b, self.size) {
+			owner.memory.dirty(self.lt);
+
+			owner.memory.dirty(self.eq);
+		}
+		else {
+			let a = owner.memory.get_int(self.a, self.size);
+
+			let b = owner.memory.get_int(self.b, self.size);
+
+			owner.memory.set(self.lt, (a < b) as CellSize);
+
+			owner.memory.set(self.eq, (a == b) as CellSize);
+		}
+
+		owner.memory.set_int(self.a, self.size, 0);
+
+		owner.memory.set_int(self.b, self.size, 0);
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		builder.instruction(owner, &mut Set::new(self.lt, 0));
+
+		let mut sub = WideSub::new(self.a, self.b, self.size, self.tmp);
+
+		sub.borrow = Some(self.lt);
+
+		builder.instruction(owner, &mut sub);
+
+		builder.instruction(owner, &mut Set::new(self.eq, 1));
+
+		builder.nl();
+
+		// Any cell left in the difference means they weren't equal
+		for i in 0..self.size {
+			builder.instruction(owner, &mut Goto::new(self.a + i)).simulate(owner);
+
+			let mut lp = builder.bfloop(false);
+
+			lp.string("[-]");
+			lp.instruction(owner, &mut Goto::new(self.eq)).simulate(owner);
+			lp.string("[-]");
+			lp.instruction(owner, &mut Goto::new(self.a + i)).simulate(owner);
+
+			builder.string(&lp.end().data);
+
+			builder.nl();
+		}
+
+		return builder.data;
+	}
+}
+
+// Long multiplication, every cell of b adds a shifted copy of a to the result that many times
+// Uses four scratch cells starting at tmp
+#[derive(Debug)]
+pub struct WideMul {
+	pub a: usize,
+
+	pub b: usize,
+
+	pub size: usize,
+
+	pub result: usize,
+
+	pub tmp: usize
+}
+
+impl WideMul {
+	pub fn new(a: usize, b: usize, size: usize, result: usize, tmp: usize) -> WideMul {
+		return WideMul { a, b, size, result, tmp };
+	}
+}
+
+impl Instruction for WideMul {
+	fn simulate(&mut self, owner: &mut Generator) {
+		owner.cell = self.a + self.size - 1;
+
+		if owner.memory.any_dirty(self.a, self.size) || owner.memory.any_dirty(self.b, self.size) {
+			for i in 0..self.size {
+				owner.memory.dirty(self.result + i);
+			}
+		}
+		else {
+			let value = owner.memory.get_int(self.a, self.size).wrapping_mul(owner.memory.get_int(self.b, self.size));
+
+			owner.memory.set_int(self.result, self.size, value);
+		}
+
+		owner.memory.set_int(self.a, self.size, 0);
+
+		owner.memory.set_int(self.b, self.size, 0);
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		let (copy, restore, flag, tmp) = (self.tmp, self.tmp + 1, self.tmp + 2, self.tmp + 3);
+
+		let cells: Vec<usize> = (self.result..self.result + self.size).collect();
+
+		for i in 0..self.size {
+			builder.instruction(owner, &mut Clear::new(self.result + i));
+		}
+
+		for j in 0..self.size {
+			builder.instruction(owner, &mut Goto::new(self.b + j)).simulate(owner);
+
+			let mut lp = builder.bfloop(false);
+
+			lp.string("-\n");
+
+			for k in 0..self.size - j {
+				lp.instruction(owner, &mut Copy::new(copy, restore, self.a + k));
+
+				lp.instruction(owner, &mut Goto::new(copy)).simulate(owner);
+
+				let mut add = lp.bfloop(false);
+
+				add.string("-");
+				add.string(&increment(owner, &cells[j + k..], flag, tmp));
+				add.instruction(owner, &mut Goto::new(copy)).simulate(owner);
+
+				lp.string(&add.end().data);
+
+				lp.nl();
+			}
+
+			lp.instruction(owner, &mut Goto::new(self.b + j)).simulate(owner);
+
+			builder.string(&lp.end().data);
+
+			builder.nl();
+		}
+
+		for i in 0..self.size {
+			builder.instruction(owner, &mut Clear::new(self.a + i));
+		}
+
+		return builder.data;
+	}
+}
+
+/*
+	Arrays are laid out as triples of marker, carry and data cells, after a guard triple and followed by a guard cell
+	G G G  M C D  M C D  ...  G
+	The walker carries the index forward in the carry cells, leaving markers to find its way back to the guard
+*/
+pub struct ArrayWalker {
+	pub base: usize,
+
+	pub length: usize,
+
+	pub index: usize,
+
+	// Result cell when reading, value cell when writing
+	pub value: usize,
+
+	pub write: bool,
+
+	// Where the index is in the source, for when the simulation finds it out of bounds
+	pub span: Span,
+
+	// Element the index resolved to at compile time, accessed directly without walking
+	known: Option<usize>
+}
+
+impl ArrayWalker {
+	pub fn read(base: usize, length: usize, index: usize, result: usize, span: Span) -> ArrayWalker {
+		return ArrayWalker { base, length, index, value: result, write: false, span, known: None };
+	}
+
+	pub fn write(base: usize, length: usize, index: usize, value: usize, span: Span) -> ArrayWalker {
+		return ArrayWalker { base, length, index, value, write: true, span, known: None };
+	}
+
+	// Cells needed for an array with this many elements
+	pub fn size(length: usize) -> usize {
+		return 3 * length + 4;
+	}
+
+	// Data cell of an element
+	pub fn element(base: usize, index: usize) -> usize {
+		return base + 5 + 3 * index;
+	}
+}
+
+impl Instruction for ArrayWalker {
+	fn simulate(&mut self, owner: &mut Generator) {
+		owner.cell = self.base;
+
+		owner.memory.set(self.index, 0);
+
+		match (self.known, self.write) {
+			(Some(k), false) => {
+				let cell = ArrayWalker::element(self.base, k);
+
+				match owner.memory.is_dirty(cell) {
+					true => owner.memory.dirty(self.value),
+
+					false => owner.memory.set(self.value, owner.memory.get(cell))
+				}
+			},
+
+			(Some(k), true) => {
+				let cell = ArrayWalker::element(self.base, k);
+
+				match owner.memory.is_dirty(self.value) {
+					true => owner.memory.dirty(cell),
+
+					false => owner.memory.set(cell, owner.memory.get(self.value))
+				}
+
+				owner.memory.set(self.value, 0);
+			},
+
+			(None, false) => owner.memory.dirty(self.value),
+
+			// Any element could have been written
+			(None, true) => {
+				for k in 0..self.length {
+					owner.memory.dirty(ArrayWalker::element(self.base, k));
+				}
+
+				owner.memory.set(self.value, 0);
+			}
+		}
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		let carry = self.base + 1;
+
+		self.known = None;
+
+		let k = match owner.memory.is_dirty(self.index) {
+			true => None,
+
+			false => Some(owner.memory.get(self.index) as usize)
+		};
+
+		// Code that never runs still walks the array, since the index there means nothing
+		if let Some(k) = k.filter(|&k| k >= self.length && ! owner.unreachable) {
+			owner.lints.borrow_mut().errors.push(CompileError::new(format!("Index {} is out of bounds for an array of length {}", k, self.length), self.span));
+		}
+
+		if let Some(k) = k.filter(|&k| k < self.length) {
+			self.known = Some(k);
+
+			let cell = ArrayWalker::element(self.base, k);
+
+			builder.instruction(owner, &mut Clear::new(self.index));
+
+			match self.write {
+				true => builder.instruction(owner, &mut Move::new(cell, self.value)),
+
+				false => builder.instruction(owner, &mut Copy::new(self.value, carry, cell))
+			};
+
+			builder.instruction(owner, &mut Goto::new(self.base)).simulate(owner);
+
+			return builder.data;
+		}
+
+		builder.instruction(owner, &mut Move::new(carry, self.index));
+
+		builder.instruction(owner, &mut Goto::new(carry)).simulate(owner);
+
+		// Walk to the element, marking every triple on the way
+		builder.string("+[-[->>>+<<<]>>+>]");
+
+		if self.write {
+			// Clear the element and go back to the guard, keeping the markers for the second walk
+			builder.string(">[-]<<[<<<]\n");
+
+			owner.cell = self.base;
+
+			builder.instruction(owner, &mut Move::new(carry, self.value));
+
+			builder.instruction(owner, &mut Goto::new(self.base + 3)).simulate(owner);
+
+			// Carry the value along the markers, store it and clear the markers on the way back
+			builder.string("[<<[->>>+<<<]>>>>>]<<[->+<]<[-<<<]\n");
+
+			owner.cell = self.base;
+
+			return builder.data;
+		}
+
+		// Copy the element into the carry cell, using the previous carry cell to restore it
+		builder.string(">[-<+<<<+>>>>]<<<<[->>>>+<<<<]>>");
+
+		// Carry it back to the guard, clearing the markers
+		builder.string("[->[-<<<+>>>]<<<<]\n");
+
+		owner.cell = self.base;
+
+		builder.instruction(owner, &mut Move::new(self.value, carry));
+
+		builder.instruction(owner, &mut Goto::new(self.base)).simulate(owner);
+
+		return builder.data;
+	}
+}
+
+// Marks cells as only known at runtime, such as variables a loop body changes on every iteration
+pub struct Runtime {
+	pub cells: Vec<usize>
+}
+
+impl Runtime {
+	pub fn new(cells: Vec<usize>) -> Runtime {
+		return Runtime { cells };
+	}
+}
+
+impl Instruction for Runtime {
+	fn simulate(&mut self, owner: &mut Generator) {
+		for &cell in &self.cells {
+			owner.memory.dirty(cell);
+		}
+	}
+
+	fn compile(&mut self, _owner: &mut Generator) -> String {
+		return String::new();
+	}
+}
+
+pub struct Output {
+	pub cell: usize
+}
+
+impl Output {
+	pub fn new(cell: usize) -> Output {
+		return Output { cell };
+	}
+}
+
+impl Instruction for Output {
+	fn simulate(&mut self, owner: &mut Generator) {
+		owner.cell = self.cell;
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		builder.instruction(owner, &mut Goto::new(self.cell)).simulate(owner);
+
+		builder.string(".");
+
+		builder.nl();
+
+		return builder.data;
+	}
+}
+
+/*
+	Prints a cell as up to three decimal digits, skipping leading zeros
+	The value is copied, so the cell is left alone
+	Block layout:
+	V 100 [ DIV ] 10 [ DIV ] T F C
+	The first division leaves the hundreds and a remainder that the second splits into tens and ones
+*/
+pub struct PrintInt {
+	pub cell: usize,
+
+	pub size: usize,
+
+	pub block: usize
+}
+
+impl PrintInt {
+	pub fn new(cell: usize, size: usize, block: usize) -> PrintInt {
+		return PrintInt { cell, size, block };
+	}
+
+	// Cells used by an integer of the given size
+	pub fn size(size: usize) -> usize {
+		return match size {
+			1 => 18,
+
+			_ => 3 * size + 8
+		};
+	}
+
+	// Decimal text of a known value
+	pub fn digits(value: u64) -> String {
+		return value.to_string();
+	}
+
+	/*
+		Wider integers are printed a digit at a time, each digit counts how often its power of ten can be subtracted
+		Leading zeros are skipped until a digit is printed, the last digit is whatever is left
+		value(n) power(n) compare(n) digit started lt eq tmp tmp output
+	*/
+	fn compile_wide(&mut self, owner: &mut Generator, builder: &mut BFBuilder) {
+		let size = self.size;
+
+		let (value, power, compare) = (self.block, self.block + size, self.block + 2 * size);
+
+		let (digit, started, lt, eq, tmp, output) = (self.block + 3 * size, self.block + 3 * size + 1, self.block + 3 * size + 2, self.block + 3 * size + 3, self.block + 3 * size + 4, self.block + 3 * size + 6);
+
+		// Everything here changes in loops that only run at runtime
+		for i in 0..PrintInt::size(size) {
+			owner.memory.dirty(self.block + i);
+		}
+
+		for i in 0..size {
+			builder.instruction(owner, &mut Copy::new(value + i, tmp, self.cell + i)).simulate(owner);
+		}
+
+		builder.instruction(owner, &mut Set::new(started, 0)).simulate(owner);
+
+		// Sets lt when the value is at least the power of ten, the power is left in place for the subtraction
+		let at_least = |check: &mut BFBlock, powers: u64| {
+			for i in 0..size {
+				check.add(Copy::new(compare + i, tmp, value + i));
+
+				check.add(Set::new(power + i, ((powers >> (8 * i)) & 0xFF) as CellSize));
+			}
+
+			check.add(WideCompare::new(compare, power, size, tmp, lt, eq));
+
+			check.add(BoolNegate::new(lt, tmp));
+
+			for i in 0..size {
+				check.add(Set::new(power + i, ((powers >> (8 * i)) & 0xFF) as CellSize));
+			}
+		};
+
+		let max = if size >= 8 { u64::MAX } else { (1 << (8 * size)) - 1 };
+
+		let mut powers = 1;
+
+		while powers <= max / 10 {
+			powers *= 10;
+		}
+
+		while powers > 1 {
+			let mut first = BFBlock::new();
+
+			first.add(Set::new(digit, 0));
+
+			at_least(&mut first, powers);
+
+			for mut instr in first.instructions {
+				builder.string(&instr.compile(owner));
+
+				instr.simulate(owner);
+			}
+
+			let mut count = While::new(lt);
+
+			count.block.add(WideSub::new(value, power, size, tmp));
+
+			count.block.add(Offset::new(digit, 1));
+
+			at_least(&mut count.block, powers);
+
+			builder.instruction(owner, &mut count).simulate(owner);
+
+			// Printed when it or a digit before it is nonzero
+			builder.instruction(owner, &mut Copy::new(output, tmp, digit)).simulate(owner);
+
+			builder.instruction(owner, &mut Copy::new(eq, tmp, started)).simulate(owner);
+
+			builder.instruction(owner, &mut Add::new(output, eq)).simulate(owner);
+
+			let mut print = If::new(output);
+
+			print.block.add(Offset::new(digit, b'0' as CellSize));
+
+			print.block.add(Output::new(digit));
+
+			print.block.add(Set::new(started, 1));
+
+			builder.instruction(owner, &mut print).simulate(owner);
+
+			powers /= 10;
+		}
+
+		builder.instruction(owner, &mut Offset::new(value, b'0' as CellSize)).simulate(owner);
+
+		builder.instruction(owner, &mut Output::new(value)).simulate(owner);
+
+		for i in 0..PrintInt::size(size) {
+			owner.memory.dirty(self.block + i);
+		}
+	}
+}
+
+impl Instruction for PrintInt {
+	fn simulate(&mut self, owner: &mut Generator) {
+		owner.cell = self.cell;
+
+		if owner.memory.any_dirty(self.cell, self.size) {
+			for i in 0..PrintInt::size(self.size) {
+				owner.memory.dirty(self.block + i);
+			}
+
+			return;
+		}
+
+		owner.memory.set(self.block, 0);
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		let block = self.block;
+
+		// Known values are printed straight from their characters
+		if ! owner.memory.any_dirty(self.cell, self.size) {
+			for digit in PrintInt::digits(owner.memory.get_int(self.cell, self.size) % (1 << (8 * self.size))).chars() {
+				builder.instruction(owner, &mut Set::new(block, digit as CellSize)).simulate(owner);
+
+				builder.instruction(owner, &mut Output::new(block)).simulate(owner);
+			}
+
+			builder.instruction(owner, &mut Clear::new(block)).simulate(owner);
+
+			builder.instruction(owner, &mut Goto::new(self.cell)).simulate(owner);
+
+			return builder.data;
+		}
+
+		if self.size > 1 {
+			self.compile_wide(owner, &mut builder);
+
+			builder.instruction(owner, &mut Goto::new(self.cell)).simulate(owner);
+
+			return builder.data;
+		}
+
+		let (hundreds, tens, ones) = (block + 7, block + 14, block + 10);
+
+		let (tmp, flag, condition) = (block + 15, block + 16, block + 17);
+
+		builder.instruction(owner, &mut Copy::new(block, tmp, self.cell)).simulate(owner);
+
+		builder.instruction(owner, &mut Set::new(block + 1, 100)).simulate(owner);
+
+		builder.instruction(owner, &mut Div { block: Some(block + 2), a: block, b: block + 1 }).simulate(owner);
+
+		builder.instruction(owner, &mut Set::new(block + 8, 10)).simulate(owner);
+
+		builder.instruction(owner, &mut Div { block: Some(block + 9), a: block + 3, b: block + 8 }).simulate(owner);
+
+		// Tens are printed when they or the hundreds are nonzero
+		builder.instruction(owner, &mut Copy::new(flag, tmp, hundreds)).simulate(owner);
+
+		builder.instruction(owner, &mut Copy::new(condition, tmp, hundreds)).simulate(owner);
+
+		let mut print = If::new(condition);
+
+		print.block.add(Offset::new(hundreds, b'0' as CellSize));
+
+		print.block.add(Output::new(hundreds));
+
+		builder.instruction(owner, &mut print).simulate(owner);
+
+		builder.instruction(owner, &mut Copy::new(condition, tmp, tens)).simulate(owner);
+
+		builder.instruction(owner, &mut Add::new(condition, flag)).simulate(owner);
+
+		let mut print = If::new(condition);
+
+		print.block.add(Offset::new(tens, b'0' as CellSize));
+
+		print.block.add(Output::new(tens));
+
+		builder.instruction(owner, &mut print).simulate(owner);
+
+		builder.instruction(owner, &mut Offset::new(ones, b'0' as CellSize)).simulate(owner);
+
+		builder.instruction(owner, &mut Output::new(ones)).simulate(owner);
+
+		builder.instruction(owner, &mut Goto::new(self.cell)).simulate(owner);
+
+		return builder.data;
+	}
+}
+
+/*
+	Prints a string up to its first zero cell, which may only be known at runtime
+	The string is copied between a zero sentinel and a terminator, so the walk can find its way back
+	0 S S S ... 0
+*/
+pub struct PrintString {
+	pub cell: usize,
+
+	pub size: usize,
+
+	pub block: usize
+}
+
+impl PrintString {
+	pub fn new(cell: usize, size: usize, block: usize) -> PrintString {
+		return PrintString { cell, size, block };
+	}
+
+	// Cells needed for the copy of a string
+	pub fn size(size: usize) -> usize {
+		return size + 2;
+	}
+}
+
+impl Instruction for PrintString {
+	fn simulate(&mut self, owner: &mut Generator) {
+		owner.cell = self.block + 1;
+
+		owner.memory.set(self.block, 0);
+
+		owner.memory.set(self.block + self.size + 1, 0);
+
+		for i in 0..self.size {
+			match owner.memory.is_dirty(self.cell + i) {
+				true => owner.memory.dirty(self.block + 1 + i),
+				false => owner.memory.set(self.block + 1 + i, owner.memory.get(self.cell + i))
+			}
+		}
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		let terminator = self.block + self.size + 1;
+
+		builder.instruction(owner, &mut Set::new(self.block, 0)).simulate(owner);
+
+		builder.instruction(owner, &mut Set::new(terminator, 0)).simulate(owner);
+
+		// The terminator doubles as the temporary cell, copying leaves it at zero
+		for i in 0..self.size {
+			builder.instruction(owner, &mut Copy::new(self.block + 1 + i, terminator, self.cell + i)).simulate(owner);
+		}
+
+		// Known strings are printed cell by cell, up to the first zero
+		if ! owner.memory.any_dirty(self.block + 1, self.size) {
+			for i in 0..self.size {
+				if owner.memory.get(self.block + 1 + i) == 0 {
+					break;
+				}
+
+				builder.instruction(owner, &mut Output::new(self.block + 1 + i)).simulate(owner);
+			}
+
+			builder.instruction(owner, &mut Goto::new(self.block + 1)).simulate(owner);
+
+			return builder.data;
+		}
+
+		builder.instruction(owner, &mut Goto::new(self.block + 1)).simulate(owner);
+
+		// Every cell before the first zero is nonzero, so walking back stops at the sentinel
+		builder.string("[.>]<[<]>");
+
+		builder.nl();
+
+		return builder.data;
+	}
+}
+
+// Text copied into the output, without any characters brainfuck would run
+pub struct Comment {
+	pub text: String
+}
+
+impl Comment {
+	pub fn new(text: &str) -> Comment {
+		return Comment { text: text.to_string() };
+	}
+}
+
+impl Instruction for Comment {
+	fn simulate(&mut self, _owner: &mut Generator) {}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		// Not every instruction ends its line
+		builder.nl();
+
+		for line in self.text.lines() {
+			let line: String = line.chars().filter(|c| ! "+-<>[],.@".contains(*c)).collect();
+
+			builder.string(&("\t".repeat(owner.indent) + line.trim()));
+
+			builder.nl();
+		}
+
+		return builder.data;
+	}
+}
+
+pub struct Command {
+	pub cell: usize
+}
+
+impl Command {
+	pub fn new(cell: usize) -> Command {
+		return Command { cell };
+	}
+}
+
+impl Instruction for Command {
+	fn simulate(&mut self, owner: &mut Generator) {
+		owner.cell = self.cell;
+
+		owner.memory.dirty(self.cell);
+	}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		let mut builder = BFBuilder::new(owner.indent);
+
+		builder.instruction(owner, &mut Goto::new(self.cell)).simulate(owner);
+
+		builder.string("@");
+
+		builder.nl();
+
+		return builder.data;
+	}
+}
+
+// Emits no code, it only reports what the simulation knows about the program at this point
+pub enum Lint {
+	// A block that runs when the condition isn't 0, and the else block when it is
+	Condition { cell: usize, has_else: bool, span: Span },
+
+	// Arithmetic whose result has to fit in size cells
+	Overflow { a: usize, b: usize, size: usize, signed: bool, operator: String, span: Span }
+}
+
+impl Instruction for Lint {
+	fn simulate(&mut self, _owner: &mut Generator) {}
+
+	fn compile(&mut self, owner: &mut Generator) -> String {
+		if owner.unreachable {
+			return String::new();
+		}
+
+		match self {
+			Lint::Condition { cell, has_else, span } => {
+				let dirty = owner.memory.is_dirty(*cell);
+
+				let met = dirty || owner.memory.get(*cell) != 0;
+
+				let unmet = dirty || owner.memory.get(*cell) == 0;
+
+				let mut lints = owner.lints.borrow_mut();
+
+				// Conditions checked more than once, like in an unrolled loop, add up every time they're checked
+				match lints.branches.iter_mut().find(|branch| branch.span == *span) {
+					Some(branch) => {
+						branch.then_runs |= met;
+
+						branch.else_runs |= unmet;
+					}
+
+					None => lints.branches.push(Branch { span: *span, has_else: *has_else, then_runs: met, else_runs: unmet })
+				}
+			}
+
+			Lint::Overflow { a, b, size, signed, operator, span } => {
+				let memory = &owner.memory;
+
+				if memory.any_dirty(*a, *size) || memory.any_dirty(*b, *size) {
+					return String::new();
+				}
+
+				let (x, y) = match signed {
+					true => (memory.get_signed(*a, *size) as i128, memory.get_signed(*b, *size) as i128),
+
+					false => (memory.get_int(*a, *size) as i128, memory.get_int(*b, *size) as i128)
+				};
+
+				let result = match operator.as_str() {
+					"+" => x + y,
+					"-" => x - y,
+					"*" => x * y,
+					_ => return String::new()
+				};
+
+				let bits = 8 * *size as u32;
+
+				let (min, max, kind) = match signed {
+					true => (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1, "a signed"),
+
+					false => (0, (1i128 << bits) - 1, "an unsigned")
+				};
+
+				if result < min || result > max {
+					owner.warn(*span, format!("{} {} {} is {}, which overflows {} {} bit integer", x, operator, y, result, kind, bits));
+				}
+			}
+		}
+
+		return String::new();
+	}
+}
+
 pub trait Instruction {
 	fn simulate(&mut self, owner: &mut Generator);
 
@@ -1187,8 +2641,13 @@ impl MemoryPool {
 	}
 
 	pub fn alloc(&mut self, size: usize) -> usize {
-		if size == 1 && self.free.len() > 0 {
-			return self.free.pop().unwrap();
+		if size == 1 && ! self.free.is_empty() {
+			let cell = self.free.pop().unwrap();
+
+			// Marked as used again, so it can be freed again
+			self.used.push(cell);
+
+			return cell;
 		}
 		
 		let block = self.current;
@@ -1222,10 +2681,11 @@ impl MemoryPool {
 
 	pub fn set(&mut self, cell: usize, value: CellSize) {
 		if cell >= self.cells.len() {
-			self.alloc(((self.cells.len() as isize) - cell as isize).abs() as usize + 1);
+			self.alloc(cell.abs_diff(self.cells.len()) + 1);
 		}
 		
-		self.cells[cell] = value;
+		// Cells are bytes at runtime, so values have to wrap the same way
+		self.cells[cell] = value % 256;
 
 		// If the cell is set, it's returned to a value known at compile time.
 		self.clean(cell);
@@ -1270,6 +2730,103 @@ impl MemoryPool {
 	pub fn is_dirty(&self, cell: usize) -> bool {
 		return self.runtime.contains(&cell);
 	}
+
+	pub fn any_dirty(&self, cell: usize, size: usize) -> bool {
+		return (cell..cell + size).any(|cell| self.is_dirty(cell));
+	}
+
+	// Value of a number stored over several cells, lowest cell first
+	pub fn get_int(&self, cell: usize, size: usize) -> u64 {
+		return (0..size).map(|i| (self.get(cell + i) as u64) << (8 * i)).sum();
+	}
+
+	// Same as get_int, but read as two's complement
+	pub fn get_signed(&self, cell: usize, size: usize) -> i64 {
+		let value = self.get_int(cell, size) as i64;
+
+		if value >> (8 * size - 1) & 1 == 1 {
+			return value - (1 << (8 * size));
+		}
+
+		return value;
+	}
+
+	pub fn set_int(&mut self, cell: usize, size: usize, value: u64) {
+		for i in 0..size {
+			self.set(cell + i, ((value >> (8 * i)) & 0xFF) as CellSize);
+		}
+	}
+
+	// Combines two possible states, any cell the states disagree on can only be known at runtime
+	pub fn merge(&self, other: &MemoryPool) -> MemoryPool {
+		let mut merged = self.clone();
+
+		if other.cells.len() > merged.cells.len() {
+			merged.alloc(other.cells.len() - merged.cells.len());
+		}
+
+		merged.current = merged.current.max(other.current);
+
+		for i in 0..merged.cells.len() {
+			let differs = match other.cells.get(i) {
+				Some(&v) => v != merged.cells[i] || other.is_dirty(i),
+				None => false
+			};
+
+			if differs && ! merged.is_dirty(i) {
+				merged.dirty(i);
+			}
+		}
+
+		return merged;
+	}
+}
+
+// Which blocks of a condition could run each time it was checked
+#[derive(Debug)]
+pub struct Branch {
+	pub span: Span,
+
+	pub has_else: bool,
+
+	pub then_runs: bool,
+
+	pub else_runs: bool
+}
+
+// What lint instructions found while compiling
+#[derive(Debug, Default)]
+pub struct Lints {
+	pub warnings: Vec<CompileError>,
+
+	// Mistakes only the simulation can find, which stop the program from compiling
+	pub errors: Vec<CompileError>,
+
+	pub branches: Vec<Branch>
+}
+
+impl Lints {
+	// One warning per place, along with every block that never ran
+	pub fn collect(&self) -> Vec<CompileError> {
+		let mut warnings: Vec<CompileError> = Vec::new();
+
+		for warning in &self.warnings {
+			if ! warnings.iter().any(|other| other.span == warning.span) {
+				warnings.push(warning.clone());
+			}
+		}
+
+		for branch in &self.branches {
+			if ! branch.then_runs {
+				warnings.push(CompileError::new("Condition is always false, so this block never runs".to_string(), branch.span));
+			}
+			else if branch.has_else && ! branch.else_runs {
+				warnings.push(CompileError::new("Condition is always true, so the else block never runs".to_string(), branch.span));
+			}
+		}
+
+		return warnings;
+	}
 }
 
 #[derive(Clone, Debug)]
@@ -1278,12 +2835,24 @@ pub struct Generator {
 
 	pub indent: usize,
 
-	pub memory: MemoryPool
+	pub memory: MemoryPool,
+
+	// Shared by every copy, so branches compiled on a copy of the state still report to the original
+	pub lints: Rc<RefCell<Lints>>,
+
+	// Set while compiling a block the simulation knows never runs, where nothing is reported
+	pub unreachable: bool
 }
 
 impl Generator {
 	pub fn new() -> Generator {
-		return Generator { cell: 0, indent: 0, memory: MemoryPool::new() };
+		return Generator { cell: 0, indent: 0, memory: MemoryPool::new(), lints: Rc::new(RefCell::new(Lints::default())), unreachable: false };
+	}
+
+	pub fn warn(&self, span: Span, message: String) {
+		if ! self.unreachable {
+			self.lints.borrow_mut().warnings.push(CompileError::new(message, span));
+		}
 	}
 	
 	pub fn indent(&self) -> String {
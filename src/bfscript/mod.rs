@@ -12,6 +12,8 @@ mod bfsanalyzer;
 
 mod bfextensions;
 
+mod bfserror;
+
 type CellSize = u32;
 
 pub use bfintrp::*;
@@ -26,7 +28,10 @@ pub use bfscompiler::*;
 
 pub use bfsanalyzer::*;
 
-use std::cell::Cell;
+// The analyzer has its own Variable and Scope, the compiler's are the ones exported
+pub use bfscompiler::{Variable, Scope};
+
+pub use bfserror::*;
 
 pub struct Debug {}
 
@@ -0,0 +1,3 @@
+pub mod bfscript;
+
+mod tests;
@@ -1,11 +1,8 @@
-use std::borrow::Borrow;
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 
-mod bfscript;
-
-mod tests;
+use bfscript_v2::bfscript;
 
 use bfscript::*;
 
@@ -55,6 +52,10 @@ fn main() {
 
 	let mut analyze = false;
 
+	let mut newline_sensitive = false;
+
+	let mut deny_warnings = false;
+
     // Example flag processing - you'll need to define your own flags
     for arg in args.iter().skip(1) {
         if arg == "-e" || arg == "--extended" {
@@ -69,6 +70,14 @@ fn main() {
 			analyze = true;
 		}
 
+		if arg == "-n" || arg == "--newline-sensitive" {
+			newline_sensitive = true;
+		}
+
+		if arg == "--deny-warnings" {
+			deny_warnings = true;
+		}
+
 		if arg == "--debug" {
 			*bfscript::DEBUG.lock().unwrap() = true;
 		}
@@ -82,6 +91,8 @@ fn main() {
 			println!("  -d --dump       Specify dump file (default: output.bfm)");
 			println!("  -e --extended   Enable extended brainfuck features (experimental)");
 			println!("  -i --interpret  Interpret compiled brainfuck, can be used with --extended");
+			println!("  -n --newline-sensitive  Reject expressions that continue on a new line where a new statement could start");
+			println!("  --deny-warnings Treat warnings as errors and don't write the output");
 			println!("  -h, --help      Display this help message");
 
 			return;
@@ -134,12 +145,50 @@ fn main() {
 			println!("{}: {:?}\n", v.name, v.value);
 		}
 
+		if ! analyzer.errors.is_empty() {
+			println!("Errors:\n");
+
+			for error in analyzer.errors {
+				println!("{}", error.render(&contents, input_file));
+			}
+		}
+
+		if ! analyzer.warnings.is_empty() {
+			println!("Warnings:\n");
+
+			for warning in analyzer.warnings {
+				println!("{}", warning.render_as("warning", &contents, input_file));
+			}
+		}
+
 		return;
 	}
 
 	let mut compiler = Compiler::new(&contents);
 
-    let output = compiler.compile();
+	compiler.parser.newline_sensitive = newline_sensitive;
+
+	compiler.analyzer.parser.newline_sensitive = newline_sensitive;
+
+    let output = compiler.compile().unwrap_or_else(|errors| {
+		for error in errors {
+			eprintln!("{}", error.render(&contents, input_file));
+		}
+
+		std::process::exit(1);
+	});
+
+	let level = if deny_warnings { "error" } else { "warning" };
+
+	for warning in &compiler.analyzer.warnings {
+		eprintln!("{}", warning.render_as(level, &contents, input_file));
+	}
+
+	if deny_warnings && ! compiler.analyzer.warnings.is_empty() {
+		eprintln!("error: {} warnings denied by --deny-warnings", compiler.analyzer.warnings.len());
+
+		std::process::exit(1);
+	}
 
     let mut file = File::create(output_file).unwrap();
 
@@ -151,4 +151,945 @@ fn dist_test() {
 	dst.simulate(&mut gen);
 
 	assert_eq!(gen.cell, 5);
-}
\ No newline at end of file
+}
+
+#[test]
+fn if_else_test() {
+	let mut gen = Generator::new();
+
+	Set::new(1, 0).simulate(&mut gen);
+
+	let mut check = IfElse::new(1, 2);
+
+	check.block.add(Set::new(3, 5));
+
+	check.else_block.add(Set::new(4, 7));
+
+	let result = check.compile(&mut gen);
+
+	assert_eq!(result.match_indices("[").count(), result.match_indices("]").count());
+
+	check.simulate(&mut gen);
+
+	assert_eq!(gen.memory.get(3), 0);
+	assert_eq!(gen.memory.get(4), 7);
+	assert_eq!(gen.cell, 2);
+}
+
+
+#[test]
+fn unroll_test() {
+	let mut gen = Generator::new();
+
+	Set::new(1, 0).simulate(&mut gen);
+
+	Set::new(2, 3).simulate(&mut gen);
+
+	Set::new(3, 0).simulate(&mut gen);
+
+	let mut unroll = Unroll::new(1, 2, 32);
+
+	// Body adds 2 to cell 3 and steps the counter
+	unroll.block.add(Set::new(4, 2));
+	unroll.block.add(Add::new(3, 4));
+	unroll.block.add(Set::new(4, 1));
+	unroll.block.add(Add::new(1, 4));
+
+	let result = unroll.compile(&mut gen);
+
+	// Fully unrolled, so no loops are emitted for the fallback
+	assert_eq!(result.match_indices("[").count(), 12);
+
+	unroll.simulate(&mut gen);
+
+	assert_eq!(gen.memory.get(1), 3);
+	assert_eq!(gen.memory.get(3), 6);
+}
+
+
+#[test]
+fn function_test() {
+	let source = b"fn pick(int a, int b) -> int {\n\tif a > b {\n\t\treturn a\n\t}\n\treturn b\n}\nint x = pick(3, 9)\nint y = pick(12, 4)";
+
+	let mut compiler = Compiler::new(source);
+
+	compiler.compile().unwrap();
+
+	let x = compiler.scope.get(&"x".to_string()).unwrap().cell;
+
+	let y = compiler.scope.get(&"y".to_string()).unwrap().cell;
+
+	assert_eq!(compiler.gen.memory.get(x), 9);
+	assert_eq!(compiler.gen.memory.get(y), 12);
+}
+
+
+#[test]
+fn semicolon_test() {
+	let mut parser = Parser::new(Tokenizer::new(b"int a = 1; a = 2;; print(a);"));
+
+	assert_eq!(parser.statement().unwrap().kind, StatementType::VarDecl);
+	assert_eq!(parser.statement().unwrap().kind, StatementType::Assignment);
+	assert_eq!(parser.statement().unwrap().kind, StatementType::Expression);
+	assert_eq!(parser.tokenizer.peek_token().unwrap().kind, TokenType::EndOfFile);
+}
+
+#[test]
+fn newline_sensitive_test() {
+	let mut parser = Parser::new(Tokenizer::new(b"a = b\n(c)"));
+
+	parser.newline_sensitive = true;
+
+	let error = parser.statement().unwrap_err();
+
+	assert!(error.message.contains("Ambiguous statement boundary"));
+	assert_eq!(error.span.line, 2);
+
+	let mut parser = Parser::new(Tokenizer::new(b"a = b\n-c"));
+
+	parser.newline_sensitive = true;
+
+	let error = parser.statement().unwrap_err();
+
+	assert_eq!(error.message, "Ambiguous statement boundary: '-' on a new line continues the expression from line 1, add ';' to end the statement or move '-' onto the same line");
+	assert_eq!(error.span, Span::new(2, 1));
+
+	// Unambiguous when the operator ends the line, or without the flag
+	let mut parser = Parser::new(Tokenizer::new(b"a = b -\nc\nd = (e)\n- f"));
+
+	parser.newline_sensitive = true;
+
+	assert!(parser.statement().is_ok());
+	assert!(parser.statement().is_err());
+
+	let mut parser = Parser::new(Tokenizer::new(b"a = b\n-c"));
+
+	assert_eq!(parser.statement().unwrap().expression.unwrap().stringify(), "(b-c)");
+}
+
+
+#[test]
+fn logic_test() {
+	let mut gen = Generator::new();
+
+	Set::new(1, 0).simulate(&mut gen);
+
+	Set::new(2, 3).simulate(&mut gen);
+
+	Set::new(4, 0).simulate(&mut gen);
+
+	// Short circuits, so the right side never runs
+	let mut and = Logic::new(false, 1, 3);
+
+	and.b = 4;
+
+	and.right.add(Set::new(4, 9));
+
+	and.compile(&mut gen);
+
+	and.simulate(&mut gen);
+
+	assert_eq!(gen.memory.get(1), 0);
+	assert_eq!(gen.memory.get(4), 0);
+
+	let mut or = Logic::new(true, 1, 3);
+
+	or.b = 2;
+
+	or.compile(&mut gen);
+
+	or.simulate(&mut gen);
+
+	assert_eq!(gen.memory.get(1), 1);
+}
+
+#[test]
+fn modulo_test() {
+	let source = b"int a = 17 % 5\nint b = 17\nb %= 5\nb += 10\nb /= 4\nint c = 7 % 0";
+
+	let mut compiler = Compiler::new(source);
+
+	compiler.compile().unwrap();
+
+	let a = compiler.scope.get(&"a".to_string()).unwrap().cell;
+
+	let b = compiler.scope.get(&"b".to_string()).unwrap().cell;
+
+	let c = compiler.scope.get(&"c".to_string()).unwrap().cell;
+
+	assert_eq!(compiler.gen.memory.get(a), 2);
+	assert_eq!(compiler.gen.memory.get(b), 3);
+	assert_eq!(compiler.gen.memory.get(c), 7);
+}
+
+#[test]
+fn precedence_test() {
+	let mut parser = Parser::new(Tokenizer::new(b"(1 + 2) * 3 - 8 / 2 * 3"));
+
+	let expr = parser.expression().unwrap();
+
+	// Subtraction is the root since * and / bind tighter and share a level
+	assert_eq!(expr.operator.as_ref().unwrap().kind, TokenType::Minus);
+	assert_eq!(expr.left.as_ref().unwrap().operator.as_ref().unwrap().kind, TokenType::Times);
+	assert_eq!(expr.right.as_ref().unwrap().operator.as_ref().unwrap().kind, TokenType::Times);
+
+	let source = b"int a = (1 + 2) * 3 - 8 / 2 * 2\nint b = -(a - 10) + 2";
+
+	let mut compiler = Compiler::new(source);
+
+	compiler.compile().unwrap();
+
+	let a = compiler.scope.get(&"a".to_string()).unwrap().cell;
+
+	let b = compiler.scope.get(&"b".to_string()).unwrap().cell;
+
+	assert_eq!(compiler.gen.memory.get(a), 1);
+	assert_eq!(compiler.gen.memory.get(b), 11);
+}
+
+#[test]
+fn break_continue_test() {
+	let source = b"int i = 0\nint sum = 0\nwhile i < 10 {\n\ti = i + 1\n\tif i == 2 {\n\t\tcontinue\n\t}\n\tif i == 5 {\n\t\tbreak\n\t}\n\tsum = sum + i\n}";
+
+	let mut compiler = Compiler::new(source);
+
+	let code = compiler.compile().unwrap();
+
+	let i = compiler.scope.get(&"i".to_string()).unwrap().cell;
+
+	let sum = compiler.scope.get(&"sum".to_string()).unwrap().cell;
+
+	let mut interpreter = Interpreter::new(code.as_bytes());
+
+	interpreter.run();
+
+	// 1 + 3 + 4, skipping 2 and stopping at 5
+	assert_eq!(interpreter.cells[i], 5);
+	assert_eq!(interpreter.cells[sum], 8);
+}
+
+#[test]
+fn array_test() {
+	let source = b"int[4] buf\nbuf[1] = 7\nint i = read(1)\nbuf[i] = 9\nint a = buf[i]\nint b = buf[1]";
+
+	let mut compiler = Compiler::new(source);
+
+	let code = compiler.compile().unwrap();
+
+	let base = compiler.scope.get(&"buf".to_string()).unwrap().cell;
+
+	let a = compiler.scope.get(&"a".to_string()).unwrap().cell;
+
+	let b = compiler.scope.get(&"b".to_string()).unwrap().cell;
+
+	// The index is only known at runtime, so the program reads it from input
+	let code = code.replace(',', "[-]+++");
+
+	let mut interpreter = Interpreter::new(code.as_bytes());
+
+	interpreter.run();
+
+	assert_eq!(interpreter.cells[ArrayWalker::element(base, 3)], 9);
+	assert_eq!(interpreter.cells[a], 9);
+	assert_eq!(interpreter.cells[b], 7);
+}
+
+#[test]
+fn wide_int_test() {
+	let source = b"u16 a = 300\na = a + 1000\nu32 b = 70000\nb = b - 4465\nu16 c = a * 3\nint lt = 0\nif a < c {\n\tlt = 1\n}\nu16 sum = 0\nint i = 0\nwhile i < 64 {\n\tsum = sum + 200\n\ti = i + 1\n}";
+
+	let mut compiler = Compiler::new(source);
+
+	let code = compiler.compile().unwrap();
+
+	let a = compiler.scope.get(&"a".to_string()).unwrap().cell;
+
+	let b = compiler.scope.get(&"b".to_string()).unwrap().cell;
+
+	let c = compiler.scope.get(&"c".to_string()).unwrap().cell;
+
+	let lt = compiler.scope.get(&"lt".to_string()).unwrap().cell;
+
+	let sum = compiler.scope.get(&"sum".to_string()).unwrap().cell;
+
+	assert_eq!(compiler.gen.memory.get_int(a, 2), 1300);
+	assert_eq!(compiler.gen.memory.get_int(b, 4), 65535);
+	assert_eq!(compiler.gen.memory.get_int(c, 2), 3900);
+	assert_eq!(compiler.gen.memory.get(lt), 1);
+
+	// The loop only runs at runtime, so the carries are checked in the interpreter
+	let mut interpreter = Interpreter::new(code.as_bytes());
+
+	interpreter.run();
+
+	assert_eq!(interpreter.cells[sum] as u32 + ((interpreter.cells[sum + 1] as u32) << 8), 12800);
+}
+
+#[test]
+fn signed_int_test() {
+	let source = b"i8 a = -5\ni8 b = 3\nint lt = 0\nif a < b {\n\tlt = 1\n}\ni8 q = -17 / b\ni8 r = -17 % b\ni16 w = a\ni16 n = 0\nint i = 0\nwhile i < 3 {\n\tn = n - 100\n\ti = i + 1\n}\nint gt = 0\nif w > n {\n\tgt = 1\n}";
+
+	let mut compiler = Compiler::new(source);
+
+	let code = compiler.compile().unwrap();
+
+	let get = |name: &str| compiler.scope.get(&name.to_string()).unwrap().cell;
+
+	assert_eq!(compiler.gen.memory.get(get("lt")), 1);
+	assert_eq!(compiler.gen.memory.get_signed(get("q"), 1), -5);
+	assert_eq!(compiler.gen.memory.get_signed(get("r"), 1), -2);
+	assert_eq!(compiler.gen.memory.get_signed(get("w"), 2), -5);
+
+	// n is only known at runtime, so the biased comparison is checked in the interpreter
+	let mut interpreter = Interpreter::new(code.as_bytes());
+
+	interpreter.run();
+
+	assert_eq!(interpreter.cells[get("gt")], 1);
+}
+
+#[test]
+fn print_int_test() {
+	let mut gen = Generator::new();
+
+	Set::new(0, 42).simulate(&mut gen);
+
+	// Known values are folded into their characters
+	let folded = PrintInt::new(0, 1, 1).compile(&mut gen);
+
+	assert_eq!(folded.matches('.').count(), 2);
+	assert!(! folded.contains("DIV"));
+
+	let mut gen = Generator::new();
+
+	let cell = gen.memory.alloc(1);
+
+	let block = gen.memory.alloc(PrintInt::size(1));
+
+	let mut code = String::new();
+
+	let mut input = Input::new(cell);
+
+	code += &input.compile(&mut gen);
+
+	input.simulate(&mut gen);
+
+	let mut print = PrintInt::new(cell, 1, block);
+
+	code += &print.compile(&mut gen);
+
+	print.simulate(&mut gen);
+
+	let code = code.replace(',', &("[-]".to_string() + &"+".repeat(205)));
+
+	let mut interpreter = Interpreter::new(code.as_bytes());
+
+	interpreter.run();
+
+	// The digits are left in the block after being printed
+	assert_eq!(interpreter.cells[block + 7], b'2');
+	assert_eq!(interpreter.cells[block + 14], b'0');
+	assert_eq!(interpreter.cells[block + 10], b'5');
+	assert_eq!(interpreter.cells[cell], 205);
+}
+
+#[test]
+fn read_int_test() {
+	let source = b"int a = read_int()\nint b = read_int()\nint c = a + b";
+
+	let mut compiler = Compiler::new(source);
+
+	let code = compiler.compile().unwrap();
+
+	let c = compiler.scope.get(&"c".to_string()).unwrap().cell;
+
+	assert!(compiler.gen.memory.is_dirty(c));
+
+	let mut interpreter = Interpreter::new(code.as_bytes());
+
+	// The end of input after 30 reads as zero
+	interpreter.input = Some("12 30".bytes().collect());
+
+	interpreter.run();
+
+	assert_eq!(interpreter.cells[c], 42);
+}
+
+#[test]
+fn read_line_test() {
+	let mut compiler = Compiler::new(b"string line = read_line(8)\nprint(line)");
+
+	let code = compiler.compile().unwrap();
+
+	let line = compiler.scope.get(&"line".to_string()).unwrap().cell;
+
+	// The print walks a copy of the buffer at runtime, so it can't be folded
+	assert!(code.contains("[.>]<[<]>"));
+
+	let mut interpreter = Interpreter::new(code.as_bytes());
+
+	interpreter.input = Some("Bob\nnext".bytes().collect());
+
+	interpreter.run();
+
+	// Stops at the newline, leaving the rest of the buffer empty
+	assert_eq!(&interpreter.cells[line..line + 4], b"Bob\0");
+	assert_eq!(interpreter.cells[line + 8], 0);
+}
+
+#[test]
+fn escape_test() {
+	let mut tokenizer = Tokenizer::new(br#""a\tb\n" '\'' '\x41' "\u{e9}" r"raw\n""#);
+
+	assert_eq!(tokenizer.next().unwrap().string, "a\tb\n");
+	assert_eq!(tokenizer.next().unwrap().char, '\'');
+	assert_eq!(tokenizer.next().unwrap().char, 'A');
+
+	// Each UTF-8 byte takes its own cell
+	assert_eq!(tokenizer.next().unwrap().string.chars().map(|c| c as u32).collect::<Vec<_>>(), vec![0xC3, 0xA9]);
+	assert_eq!(tokenizer.next().unwrap().string, "raw\\n");
+}
+
+#[test]
+fn malformed_escape_test() {
+	let mut tokenizer = Tokenizer::new(b"\"fine\"\n\"\\x4\"");
+
+	tokenizer.next().unwrap();
+
+	let error = tokenizer.next().unwrap_err();
+
+	assert!(error.message.contains("Invalid escape sequence"));
+	assert_eq!(error.span.line, 2);
+}
+
+#[test]
+fn number_literal_test() {
+	let mut tokenizer = Tokenizer::new(b"0x41 0b1010 0o17 1_000 0xFF_FF");
+
+	let numbers: Vec<i64> = (0..5).map(|_| tokenizer.next().unwrap().number).collect();
+
+	assert_eq!(numbers, vec![0x41, 10, 15, 1000, 0xFFFF]);
+
+	let mut compiler = Compiler::new(b"char c = 'a' + 1\nint d = 1 + 'a'\nint e = 'z' - 'a'");
+
+	compiler.compile().unwrap();
+
+	let get = |name: &str| compiler.gen.memory.get(compiler.scope.get(&name.to_string()).unwrap().cell);
+
+	assert_eq!(get("c"), 'b' as u32);
+	assert_eq!(get("d"), 'b' as u32);
+	assert_eq!(get("e"), 25);
+}
+
+#[test]
+fn literal_range_test() {
+	let errors = Compiler::new(b"i8 x = 200").compile().unwrap_err();
+
+	assert!(errors[0].message.contains("200 is out of range for i8"));
+
+	// A literal too wide for the type is only out of range, not a mismatch as well
+	for source in [&b"int x = 0x1FF"[..], b"int x = 1\nx = 0x1FF", b"int[2] arr\narr[0] = 0x1FF", b"fn f(int a) {\n\tprint_int(a)\n}\nf(0x1FF)"] {
+		let mut analyzer = Analyzer::new(source);
+
+		analyzer.analyze();
+
+		assert_eq!(analyzer.errors.len(), 1, "{:?}", analyzer.errors);
+		assert!(analyzer.errors[0].message.contains("511 is out of range for int"));
+	}
+}
+
+#[test]
+fn comment_test() {
+	let source = b"/* outer /* nested */ still outer */\n/// Counts [things], + more\nint a = 1 /* inline */ + 2\n// last line without a newline";
+
+	let mut compiler = Compiler::new(source);
+
+	let code = compiler.compile().unwrap();
+
+	let a = compiler.scope.get(&"a".to_string()).unwrap().cell;
+
+	assert_eq!(compiler.gen.memory.get(a), 3);
+
+	// The doc comment is kept without anything brainfuck would run
+	assert!(code.contains("Counts things  more"));
+
+	let mut parser = Parser::new(Tokenizer::new(b"/// first\n/// second\nfn f() {\n}\n//// not a doc\nint b = 1"));
+
+	assert_eq!(parser.statement().unwrap().doc, Some("first\nsecond".to_string()));
+	assert_eq!(parser.statement().unwrap().doc, None);
+
+	// Constants are documented where they're declared, other statements can't be documented
+	let mut compiler = Compiler::new(b"/// The answer\nconst X = 42\nint a = X\n/// Nothing to document\na = 2\nprint_int(a)");
+
+	let code = compiler.compile().unwrap();
+
+	assert!(code.contains("The answer"));
+
+	let warnings: Vec<(Span, &str)> = compiler.analyzer.warnings.iter().map(|warning| (warning.span, warning.message.as_str())).collect();
+
+	assert_eq!(warnings, vec![
+		(Span::new(3, 5), "Value assigned to a is never read"),
+		(Span::new(5, 1), "Doc comment is ignored, only variables, constants and functions are documented")
+	]);
+}
+
+#[test]
+fn const_test() {
+	let source = b"const BASE = 0x40\nconst NEXT = BASE + 1\nconst WIDE = 1000 * 3\nint a = NEXT\nu16 w = WIDE";
+
+	let mut compiler = Compiler::new(source);
+
+	let code = compiler.compile().unwrap();
+
+	assert_eq!(compiler.constants["NEXT"], 0x41);
+	assert_eq!(compiler.constants["WIDE"], 3000);
+
+	// Constants take no cells of their own
+	assert!(compiler.scope.get(&"NEXT".to_string()).is_none());
+
+	let a = compiler.scope.get(&"a".to_string()).unwrap().cell;
+
+	let w = compiler.scope.get(&"w".to_string()).unwrap().cell;
+
+	assert_eq!(compiler.gen.memory.get(a), 0x41);
+	assert_eq!(compiler.gen.memory.get_int(w, 2), 3000);
+	assert!(! code.contains(','));
+}
+
+#[test]
+fn const_input_test() {
+	let errors = Compiler::new(b"const N = read_int()").compile().unwrap_err();
+
+	assert!(errors[0].message.contains("Constant N depends on input"));
+}
+
+#[test]
+fn scope_test() {
+	let block = "int x = 1\nif x {\n\tint x = 5\n\tint y = x + 1\n\tx = y\n}\n";
+
+	let mut compiler = Compiler::new(block.as_bytes());
+
+	compiler.compile().unwrap();
+
+	let used = compiler.gen.memory.cells.len();
+
+	let source = format!("{}int z = x\nint w = 9", block);
+
+	let mut compiler = Compiler::new(source.as_bytes());
+
+	compiler.compile().unwrap();
+
+	// The inner x only shadows the outer one
+	let x = compiler.scope.get(&"x".to_string()).unwrap().cell;
+
+	let z = compiler.scope.get(&"z".to_string()).unwrap().cell;
+
+	assert_eq!(compiler.gen.memory.get(x), 1);
+	assert_eq!(compiler.gen.memory.get(z), 1);
+	assert!(compiler.scope.get(&"y".to_string()).is_none());
+
+	// Cells from the block are reused afterwards
+	assert_eq!(compiler.gen.memory.cells.len(), used);
+}
+
+#[test]
+fn scope_end_test() {
+	let errors = Compiler::new(b"while 0 {\n\tint y = 2\n}\nprint(y)").compile().unwrap_err();
+
+	assert!(errors[0].message.contains("Variable y is used outside of the block it was declared in"));
+	assert_eq!(errors[0].span, Span::new(4, 7));
+}
+
+#[test]
+fn type_check_test() {
+	let mut analyzer = Analyzer::new(b"int a = \"hi\"\nstring s = 5\nint b = \"hi\" + 1\nprint_int(q)\nfoo(1)\nchar c = 'x'\nstring t = c\nint d = c + 1");
+
+	analyzer.analyze();
+
+	let lines: Vec<u32> = analyzer.errors.iter().map(|error| error.span.line).collect();
+
+	// Every error is found, each at its own line
+	assert_eq!(lines, vec![1, 2, 3, 4, 5]);
+	assert!(analyzer.errors[2].message.contains("Operator + can't be applied to string and int"));
+	assert!(analyzer.errors[3].message.contains("Variable q not found"));
+}
+
+#[test]
+fn error_render_test() {
+	let source = b"int a = 1\nint b = q";
+
+	let error = CompileError::new("Variable q not found".to_string(), Span::new(2, 9));
+
+	let rendered = error.render(source, "main.bfs");
+
+	assert_eq!(rendered, "error: Variable q not found\n --> main.bfs:2:9\n  |\n2 | int b = q\n  |         ^\n");
+	assert_eq!(error.to_string(), "2:9: Variable q not found");
+}
+
+#[test]
+fn error_recovery_test() {
+	let mut parser = Parser::new(Tokenizer::new(b"int a = 1\nint b = )\nprint(a)\nif a {\n\tint c = * 2\n\tc = 3\n}\nprint_int(a); int d = ); int e = 2"));
+
+	let program = parser.parse();
+
+	let lines: Vec<u32> = parser.errors.iter().map(|error| error.span.line).collect();
+
+	// Every syntax error is found, and the statements around them are kept
+	assert_eq!(lines, vec![2, 5, 8]);
+	assert_eq!(program.iter().map(|stmt| stmt.kind.clone()).collect::<Vec<_>>(), vec![StatementType::VarDecl, StatementType::Expression, StatementType::If, StatementType::Expression, StatementType::VarDecl]);
+	assert_eq!(program[2].block.as_ref().unwrap().len(), 1);
+
+	let errors = Compiler::new(b"int a = )\nint b = *").compile().unwrap_err();
+
+	assert_eq!(errors.len(), 2);
+}
+
+#[test]
+fn lookahead_error_test() {
+	let mut parser = Parser::new(Tokenizer::new(b"int y = 1 +\nint w = 1 #\nstring q = \"abc\\q\""));
+
+	parser.parse();
+
+	let errors: Vec<(Span, &str)> = parser.errors.iter().map(|error| (error.span, error.message.as_str())).collect();
+
+	// A token that can't be read a few tokens ahead belongs to the statement that reads it
+	assert_eq!(errors, vec![
+		(Span::new(2, 11), "Expected an expression but got invalid character '#'"),
+		(Span::new(3, 16), "Invalid escape sequence: \\q")
+	]);
+}
+
+#[test]
+fn token_position_test() {
+	// Line and column of a byte offset, counted the same way the tokenizer does
+	fn position_of(source: &[u8], offset: usize) -> (u32, u32) {
+		let before = &source[..offset];
+
+		let line = before.iter().filter(|&&c| c == b'\n').count() as u32 + 1;
+
+		let column = (offset - before.iter().rposition(|&c| c == b'\n').map_or(0, |newline| newline + 1)) as u32 + 1;
+
+		return (line, column);
+	}
+
+	let source = b"int x = 0x1F // note\n/* block\n comment */ string s = \"a\\tb\"\nfor i in 0..3 { x += 'c' } -> != r\"raw\";\n\tprint(s[1])";
+
+	let mut tokenizer = Tokenizer::new(source);
+
+	let mut texts = Vec::new();
+
+	loop {
+		let token = tokenizer.next().unwrap();
+
+		if token.kind == TokenType::EndOfFile {
+			assert_eq!((token.line, token.column), position_of(source, source.len()));
+
+			break;
+		}
+
+		// Positions start at the first character of the token and end right after its last
+		assert_eq!((token.line, token.column), position_of(source, token.start));
+		assert_eq!((token.end_line, token.end_column), position_of(source, token.end));
+
+		texts.push(String::from_utf8_lossy(&source[token.start..token.end]).to_string());
+	}
+
+	assert_eq!(texts, vec![
+		"int", "x", "=", "0x1F",
+		"string", "s", "=", "\"a\\tb\"",
+		"for", "i", "in", "0", "..", "3", "{", "x", "+=", "'c'", "}", "->", "!=", "r\"raw\"", ";",
+		"print", "(", "s", "[", "1", "]", ")"
+	]);
+}
+
+#[test]
+fn token_column_test() {
+	let mut tokenizer = Tokenizer::new(b"a+(b)==c");
+
+	let columns: Vec<u32> = (0..7).map(|_| tokenizer.next().unwrap().column).collect();
+
+	// Single character tokens used to push every later column one further
+	assert_eq!(columns, vec![1, 2, 3, 4, 5, 6, 8]);
+
+	let mut tokenizer = Tokenizer::new(b"x = 'ab'");
+
+	// A bad token after the first is left for whoever reads it
+	let tokens = tokenizer.peek_many(3).unwrap();
+
+	assert_eq!(tokens[2].kind, TokenType::Error);
+	assert_eq!(tokens[2].span(), Span::new(1, 5));
+
+	tokenizer.next().unwrap();
+	tokenizer.next().unwrap();
+
+	assert_eq!(tokenizer.next().unwrap_err().span, Span::new(1, 5));
+	assert!(Tokenizer::new(b"'ab'").peek_many(3).is_err());
+}
+
+#[test]
+fn invalid_character_test() {
+	let mut tokenizer = Tokenizer::new(b"a # \x80b");
+
+	let kinds: Vec<TokenType> = (0..5).map(|_| tokenizer.next().unwrap().kind).collect();
+
+	assert_eq!(kinds, vec![TokenType::Identifier, TokenType::Error, TokenType::Error, TokenType::Identifier, TokenType::EndOfFile]);
+
+	let errors = Compiler::new(b"int a = 1\nint b = @\nint c = $").compile().unwrap_err();
+
+	assert_eq!(errors.len(), 2);
+	assert!(errors[0].message.contains("invalid character '@'"));
+}
+
+// The same checks as fuzz/fuzz_targets/parse.rs on a fixed set of inputs, so they run with cargo test
+#[test]
+fn tokenizer_fuzz_test() {
+	let program: &[u8] = b"/// doc\nfn f(int a) -> int { return a * 2 }\n/* a /* nested */ comment */\nstring s = \"a\\x41\\u{e9}\\n\"\nchar c = '\\''\ni16 n = -0x7F_FF + 0b1 % 0o7\nfor i in 0..3 { if i >= 1 && i != 2 || !i { s[0] = r\"raw\" } else { break } }\n// end";
+
+	// Every prefix of a program that uses each kind of token, so input can end anywhere
+	let mut inputs: Vec<Vec<u8>> = (0..=program.len()).map(|n| program[..n].to_vec()).collect();
+
+	// Then random bytes from a fixed seed, mostly the ones tokens start with
+	let alphabet = b"\"'\\/*{}()[]<>=!&|+-%.,;_0xbou{}\n\t azAZ09#@~\x80\xFF";
+
+	let mut seed: u64 = 0x9E3779B97F4A7C15;
+
+	let mut random = || {
+		seed ^= seed << 13;
+		seed ^= seed >> 7;
+		seed ^= seed << 17;
+
+		return seed;
+	};
+
+	for _ in 0..3000 {
+		let length = random() % 40;
+
+		let input = (0..length).map(|_| {
+			let r = random();
+
+			if r % 4 == 0 { (r >> 8) as u8 } else { alphabet[(r >> 8) as usize % alphabet.len()] }
+		}).collect();
+
+		inputs.push(input);
+	}
+
+	for input in &inputs {
+		let mut tokenizer = Tokenizer::new(input);
+
+		loop {
+			let start = tokenizer.position;
+
+			match tokenizer.next() {
+				Ok(token) if token.kind == TokenType::EndOfFile => break,
+
+				// Every token takes at least one byte, so reading always comes to an end
+				Ok(token) => assert!(tokenizer.position > start && token.end <= input.len()),
+
+				Err(error) => {
+					assert!(error.span.line >= 1 && error.span.column >= 1);
+
+					break;
+				}
+			}
+		}
+
+		// Recovering from whatever the tokenizer reports has to end too
+		Parser::new(Tokenizer::new(input)).parse();
+	}
+}
+
+#[test]
+fn lint_test() {
+	let mut analyzer = Analyzer::new(b"int unused = 3\nint _skipped = 1\nint x = 1\nx = 2\nprint_int(x)\nint c = read_int()\nint y = 1\nif c {\n\ty = 2\n}\nprint_int(y)\nx = 5");
+
+	analyzer.analyze();
+
+	let warnings: Vec<(u32, &str)> = analyzer.warnings.iter().map(|warning| (warning.span.line, warning.message.as_str())).collect();
+
+	// A store that's only overwritten in a branch may still be read
+	assert_eq!(warnings, vec![
+		(1, "Variable unused is never used"),
+		(3, "Value assigned to x is never read"),
+		(12, "Value assigned to x is never read")
+	]);
+
+	let mut compiler = Compiler::new(b"int debug = 0\nif debug {\n\tprint(\"debug\")\n}\nfor i in 0..3 {\n\tif i == 1 {\n\t\tprint(\"one\")\n\t}\n}\nint big = 200 + 100\nprint_int(big)\ni8 small = 100\ni8 sum = small + 100\nprint_int(sum)");
+
+	compiler.compile().unwrap();
+
+	let warnings: Vec<(Span, &str)> = compiler.analyzer.warnings.iter().map(|warning| (warning.span, warning.message.as_str())).collect();
+
+	// The condition inside the unrolled loop is met once, so it's left alone
+	assert_eq!(warnings, vec![
+		(Span::new(2, 4), "Condition is always false, so this block never runs"),
+		(Span::new(10, 15), "200 + 100 is 300, which overflows an unsigned 8 bit integer"),
+		(Span::new(13, 16), "100 + 100 is 200, which overflows a signed 8 bit integer")
+	]);
+
+	// Functions read the variables in scope where they're called, including through other functions
+	let source = b"fn f() -> int {\n\treturn x\n}\nfn g() -> int {\n\treturn f() + y\n}\nint x = 5\nint y = 1\nprint_int(g())\nint z = 2";
+
+	let mut analyzer = Analyzer::new(source);
+
+	analyzer.analyze();
+
+	let warnings: Vec<(u32, &str)> = analyzer.warnings.iter().map(|warning| (warning.span.line, warning.message.as_str())).collect();
+
+	assert_eq!(warnings, vec![(10, "Variable z is never used")]);
+	assert_eq!(run(source, ""), "6");
+}
+
+// Compiles and runs a program, returning what it printed
+#[cfg(test)]
+fn run(source: &[u8], input: &str) -> String {
+	let code = Compiler::new(source).compile().unwrap();
+
+	let mut interpreter = Interpreter::new(code.as_bytes());
+
+	interpreter.input = Some(input.bytes().collect());
+
+	interpreter.output = Some(Vec::new());
+
+	interpreter.run();
+
+	return String::from_utf8_lossy(&interpreter.output.unwrap()).to_string();
+}
+
+#[test]
+fn runtime_mul_test() {
+	assert_eq!(run(b"print_int(read_int() * 2)", "7"), "14");
+	assert_eq!(run(b"int a = read(1)\nint b = a * 2\nprint_int(b)\nprint(b)", "\x07"), "14\x0E");
+
+	// Products in a loop body are only known at runtime
+	assert_eq!(run(b"int n = read_int()\nint i = 0\nwhile i < n {\n\tint t = i * 2\n\tprint_int(t)\n\ti = i + 1\n}", "3"), "024");
+}
+
+#[test]
+fn array_index_test() {
+	// The index is a product of a runtime value, so the element is picked at runtime
+	assert_eq!(run(b"int a = read_int()\nint[4] arr\nint i = a * 1\narr[i] = 5\nprint_int(arr[2])\nprint_int(arr[0])", "2"), "50");
+
+	for (source, line) in [(&b"int[3] arr\narr[2 + 3] = 1"[..], 2), (b"int[3] arr\nprint_int(arr['a'])", 2), (b"int[3] arr\nint i = 9\narr[i] = 1", 3)] {
+		let errors = Compiler::new(source).compile().unwrap_err();
+
+		assert!(errors[0].message.contains("is out of bounds for an array of length 3"));
+		assert_eq!(errors[0].span.line, line);
+	}
+}
+
+#[test]
+fn unsigned_compare_test() {
+	let compare = b"int a = read_int()\nint b = read_int()\nprint_int(a > b)\nprint_int(a >= b)\nprint_int(a < b)\nprint_int(a <= b)";
+
+	// b is 0, and the two sides are as far apart as a cell allows
+	assert_eq!(run(compare, "1 0"), "1100");
+	assert_eq!(run(compare, "0 0"), "0101");
+	assert_eq!(run(compare, "0 255"), "0011");
+	assert_eq!(run(compare, "255 0"), "1100");
+
+	// Known values are compared while compiling, the same way
+	assert_eq!(run(b"print_int(1 > 0)\nprint_int(1 <= 0)\nprint_int(0 <= 255)\nprint_int(255 > 0)", ""), "1011");
+
+	assert_eq!(run(b"int n = read_int()\nwhile n > 0 {\n\tprint_int(n)\n\tn = n - 1\n}", "3"), "321");
+}
+
+#[test]
+fn recursion_test() {
+	let errors = Compiler::new(b"fn f() -> int {\n\treturn f()\n}\nprint_int(f())").compile().unwrap_err();
+
+	assert_eq!(errors.len(), 1);
+	assert_eq!(errors[0].message, "Recursive call to f can't be inlined");
+	assert_eq!(errors[0].span, Span::new(2, 9));
+
+	// Found when b is declared, whether or not anything calls them
+	for source in [&b"fn a() {\n\tb()\n}\nfn b() {\n\ta()\n}\na()"[..], b"fn a() {\n\tb()\n}\nfn b() {\n\ta()\n}"] {
+		let errors = Compiler::new(source).compile().unwrap_err();
+
+		assert_eq!(errors[0].message, "Recursive call to b can't be inlined");
+		assert_eq!(errors[0].span, Span::new(2, 2));
+	}
+}
+
+#[test]
+fn function_check_test() {
+	let mut analyzer = Analyzer::new(b"fn f() -> int {\n\treturn \"abc\" + undefined_var\n}\nfn g(int a, string s) -> int {\n\tstring t = \"ab\"\n\tt = \"abcde\"\n\treturn a + s\n}");
+
+	analyzer.analyze();
+
+	let errors: Vec<(Span, &str)> = analyzer.errors.iter().map(|error| (error.span, error.message.as_str())).collect();
+
+	// Bodies are checked even though nothing calls them
+	assert_eq!(errors, vec![
+		(Span::new(2, 15), "Operator + can't be applied to string and Unknown"),
+		(Span::new(6, 6), "Type mismatch: string(5) doesn't fit in string(2)"),
+		(Span::new(2, 17), "Variable undefined_var not found")
+	]);
+
+	// Names declared after the function are fine when it's called where they exist
+	let mut analyzer = Analyzer::new(b"fn f() -> int {\n\treturn x + h()\n}\nfn h() -> int {\n\treturn 1\n}\nint x = 5\nprint_int(f())");
+
+	analyzer.analyze();
+
+	assert!(analyzer.errors.is_empty(), "{:?}", analyzer.errors);
+}
+
+#[test]
+fn runtime_lint_test() {
+	let mut compiler = Compiler::new(b"int a = read(1)\nint b = a * 2\nif b == 14 {\n\tprint(\"x\")\n}\nint c = a * b + 1\nwhile c > 200 {\n\tc = c - 1\n}\nprint_int(c)");
+
+	compiler.compile().unwrap();
+
+	// Products of runtime values aren't known, so nothing about them can be proven
+	assert!(compiler.analyzer.warnings.is_empty(), "{:?}", compiler.analyzer.warnings);
+}
+
+#[test]
+fn char_string_test() {
+	// A one character string is a character wherever a number is expected
+	assert_eq!(run(b"int c = read(1) + 1\nprint_int(c)\nprint_int(read(1) == 98)\nprint_int(read(1) * 2)\nif read(1) {\n\tprint(\"y\")\n}\nprint_int(read(1) - 1)", "abcde"), "981198y100");
+	assert_eq!(run(b"print_int(\"a\")\nprint_int(\"a\" + 1)", ""), "9798");
+
+	let mut analyzer = Analyzer::new(b"print_int(\"ab\" + 1)\nint x = -read(1)");
+
+	analyzer.analyze();
+
+	assert_eq!(analyzer.errors.len(), 1);
+	assert_eq!(analyzer.errors[0].message, "Operator + can't be applied to string and int");
+}
+
+
+#[test]
+fn compound_index_test() {
+	// The index is read once, so the element that's read is the one that's written
+	assert_eq!(run(b"int[4] buf\nbuf[2] = 5\nbuf[read_int()] += 1\nprint_int(buf[2])\nprint_int(buf[1])", "2 1"), "60");
+	assert_eq!(run(b"int[4] buf\nint i = read_int()\nbuf[i] = 3\nbuf[i] *= 4\nbuf[i + 1] = 1\nbuf[i + 1] -= 1\nprint_int(buf[i])\nprint_int(buf[i + 1])", "1"), "120");
+
+	let mut analyzer = Analyzer::new(b"int[4] buf\nbuf[read_int()] += 1\nbuf[read_int()] += 2\nprint_int(buf[0])");
+
+	analyzer.analyze();
+
+	assert!(analyzer.errors.is_empty() && analyzer.warnings.is_empty(), "{:?} {:?}", analyzer.errors, analyzer.warnings);
+}
+
+#[test]
+fn not_equal_test() {
+	// 0 or 1, not the difference of the two sides
+	assert_eq!(run(b"int a = read_int()\nint b = read_int()\nint c = read_int()\nint d = read_int()\nprint_int(a != b)\nint t = (a != b) + (c != d)\nprint_int(t)\nif a != b {\n\tprint(\"y\")\n}", "0 1 9 3"), "12y");
+	assert_eq!(run(b"int a = read_int()\nprint_int(a != 5)\nprint_int(a != 7)", "7"), "10");
+	assert_eq!(run(b"print_int(3 != 1)\nprint_int(4 != 4)", ""), "10");
+}
+
+#[test]
+fn print_wide_test() {
+	// Known values of any width are printed while compiling
+	assert_eq!(run(b"const X = 300\nprint_int(X)\nu16 a = 65535\nprint_int(a)\ni16 b = -1234\nprint_int(b)\nu32 c = 4000000000\nprint_int(c)", ""), "30065535-12344000000000");
+
+	// Runtime values are printed a digit at a time, with no leading zeros
+	let wide = b"u16 a = read_int()\na = a + 62250\nprint_int(a)\nprint(\" \")\nu16 b = read_int()\nprint_int(b)\nprint(\" \")\ni16 c = read_int()\nc = 0 - (c + 19900)\nprint_int(c)";
+
+	assert_eq!(run(wide, "250 7 100"), "62500 7 -20000");
+	assert_eq!(run(wide, "0 0 0"), "62250 0 -19900");
+	assert_eq!(run(b"u32 a = read_int()\na = a + 16777000\nprint_int(a)", "255"), "16777255");
+}
+
+